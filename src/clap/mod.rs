@@ -2,6 +2,77 @@ use std::path::PathBuf;
 
 use clap::{arg, command, value_parser, ArgAction, Command};
 use owo_colors::OwoColorize;
+use serde_yaml::{Mapping, Value};
+
+/// Command-line arguments. Anything other than the configuration file overrides
+/// the matching part of it.
+pub struct Args {
+    pub config: PathBuf,
+    /// A file, a URL, or `-` for stdin.
+    pub input: Option<String>,
+    /// An output path, or `-` for stdout.
+    pub output: Option<String>,
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        let matches = command!()
+            .arg(
+                arg!(<config> "The configuration file to use.")
+                    .value_parser(value_parser!(PathBuf)),
+            )
+            .arg(
+                arg!(-i --input <SOURCE> "Overrides the source - a file, a URL, or `-` for stdin.")
+                    .required(false),
+            )
+            .arg(
+                arg!(-o --output <PATH> "Overrides the output path - `-` writes to stdout.")
+                    .required(false),
+            )
+            .get_matches();
+
+        Self {
+            config: matches.get_one::<PathBuf>("config").unwrap().clone(),
+            input: matches.get_one::<String>("input").cloned(),
+            output: matches.get_one::<String>("output").cloned(),
+        }
+    }
+
+    /// Applies the overrides to a parsed configuration file.
+    pub fn apply(&self, config: &mut Value) {
+        if let Some(input) = &self.input {
+            let source = Self::section(config, "source");
+            for key in ["file", "url", "stdin"] {
+                source.remove(key);
+            }
+
+            if input == "-" {
+                source.insert("stdin".into(), true.into());
+            } else if input.starts_with("http://") || input.starts_with("https://") {
+                source.insert("url".into(), input.as_str().into());
+            } else {
+                source.insert("file".into(), input.as_str().into());
+            }
+        }
+
+        if let Some(output) = &self.output {
+            Self::section(config, "output").insert("path".into(), output.as_str().into());
+        }
+    }
+
+    /// Gets a top-level section of the configuration, creating it if needed.
+    fn section<'a>(config: &'a mut Value, key: &str) -> &'a mut Mapping {
+        let config = config
+            .as_mapping_mut()
+            .expect("the configuration must be a mapping.");
+
+        config
+            .entry(key.into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()))
+            .as_mapping_mut()
+            .unwrap_or_else(|| panic!("[{key}] must be a mapping."))
+    }
+}
 
 pub fn clap_demo() {
     clap();
@@ -1,12 +1,11 @@
-use std::{error::Error, path::Path, time::Duration};
+use std::{error::Error, io::Write, path::Path, time::Duration};
 
-use image::DynamicImage;
 use indicatif::{ProgressBar, ProgressStyle};
 use owo_colors::OwoColorize;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 
 use crate::{
-    parsers::v2::structure::MainConfiguration,
+    parsers::v2::structure::{output::NameVars, value::seed_iteration, MainConfiguration},
     utils::resource::Resource,
 };
 
@@ -21,7 +20,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     // crate::clap::clap_demo();
     // crate::ffmpeg::FfmpegUtil::demo();
 
-    let mut args = std::env::args();
+    let args = crate::clap::Args::parse();
 
     let label_processing = "[...]".blue();
     let label_info = "[ @ ]".purple();
@@ -31,21 +30,18 @@ fn main() -> Result<(), Box<dyn Error>> {
     let label_info = label_info.bold();
     let label_alert = label_alert.bold();
 
-    if args.len() != 2 {
-        panic!("Expected a single arg which represents the filepath of the configuration file.");
-    }
-
-    let config_file = args.nth(1).unwrap();
+    let config_file = args.config.display().to_string();
 
-    println!("{label_processing} | Reading configuration file: {config_file}");
+    eprintln!("{label_processing} | Reading configuration file: {config_file}");
 
     let config = std::fs::read_to_string(config_file)?;
 
-    println!("{label_processing} | Parsing file as YAML...");
+    eprintln!("{label_processing} | Parsing file as YAML...");
 
-    let yaml: serde_yaml::Value = serde_yaml::from_str(&config)?;
+    let mut yaml: serde_yaml::Value = serde_yaml::from_str(&config)?;
+    args.apply(&mut yaml);
 
-    println!("{label_processing} | Parsing YAML as configuration");
+    eprintln!("{label_processing} | Parsing YAML as configuration");
 
     // let rng = StdRng::from_os_rng();
 
@@ -65,7 +61,17 @@ fn main() -> Result<(), Box<dyn Error>> {
     //     }
     // };
 
-    let out_path = maincfg.output.path;
+    let to_stdout = maincfg.output.is_stdout();
+    if to_stdout && maincfg.output.n != 1 {
+        panic!("Writing to stdout needs exactly one iteration - set `n: 1`.");
+    }
+
+    // results for stdout are saved somewhere temporary first, then streamed out.
+    let out_path = &if to_stdout {
+        format!("temp/stdout-{}", std::process::id())
+    } else {
+        maincfg.output.path.clone()
+    };
     if !Path::new(&out_path.clone()).is_dir() {
         std::fs::create_dir_all(&out_path)?;
     }
@@ -92,30 +98,37 @@ fn main() -> Result<(), Box<dyn Error>> {
     // log.state_property("n", iterations.to_string())?;
     // log.end_category()?; // output
 
-    println!(
+    eprintln!(
         "{label_processing} | Processing image: {}",
         maincfg.source.kind.get_path()
     );
-    println!(
+    eprintln!(
         "      | ...with constraint: {}",
         maincfg.source.constraint_str()
     );
+    if maincfg.source.trim.is_trimmed() {
+        eprintln!("      | ...trimmed to: {}", maincfg.source.trim.as_string());
+    }
 
     // let media = maincfg.source.perform();
-    let mut media = Resource::use_source(&maincfg.source.kind);
+    let mut media = Resource::use_source(&maincfg.source, &maincfg.ffmpeg);
     if let Some(constraint) = maincfg.source.constraint {
         media = media.constrain(&constraint);
     }
 
     let dims = media.get_dimensions();
 
-    println!("{label_info} | Image dimensions are: {dims:?}]");
-    println!("      | Total pixels: {}", dims.0 * dims.1);
+    eprintln!("{label_info} | Image dimensions are: {dims:?}]");
+    eprintln!("      | Total pixels: {}", dims.0 * dims.1);
 
-    println!("{label_alert} | Running {iterations} iterations...");
+    eprintln!("{label_alert} | Running {iterations} iterations...");
 
     // TODO: Add initial setup.
 
+    // each iteration is seeded from this, so any of them can be reproduced later.
+    let base_seed = maincfg.output.seed.unwrap_or_else(rand::random);
+    let stem = maincfg.source.kind.stem();
+
     let bar = ProgressBar::new(iterations);
     bar.set_style(
         ProgressStyle::with_template(
@@ -133,11 +146,29 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         let mut media_for_iteration = media.with_prefix(format!("output-{i:04}"));
 
-        let effects = maincfg.effects.generate::<DynamicImage>();
+        let seed = base_seed.wrapping_add(i);
+        seed_iteration(seed);
 
-        media_for_iteration = media_for_iteration.apply_effects(effects);
+        let effects = maincfg.effects.generate();
+        let effect_names = effects.names().join("+");
 
-        media_for_iteration.save(&format!("{out_path}/{i:<05}"));
+        media_for_iteration = media_for_iteration.apply_effects(effects, &maincfg.alpha);
+
+        let name = maincfg.output.name.render(&NameVars {
+            stem: &stem,
+            i: i as usize,
+            seed,
+            effects: effect_names,
+        });
+        let extension = media_for_iteration.extension(&maincfg.output);
+        let saved = maincfg.output.reserve_path(out_path, &name, extension);
+
+        media_for_iteration.save(&saved, &maincfg.output);
+
+        if to_stdout {
+            let bytes = std::fs::read(&saved).unwrap();
+            std::io::stdout().lock().write_all(&bytes).unwrap();
+        }
 
         media_for_iteration.clear_temp();
 
@@ -145,11 +176,18 @@ fn main() -> Result<(), Box<dyn Error>> {
         // log.newline()?;
     });
 
+    // clears anything extracted from the source itself (e.g. audio).
+    media.clear_temp();
+
+    if to_stdout {
+        std::fs::remove_dir_all(out_path)?;
+    }
+
     let dur = bar.duration();
     let h = dur.as_secs() / (60 * 60);
     let m = dur.as_secs() / (60) % 60;
     let s = dur.as_secs() % 60;
-    println!("done in {h:0>2}:{m:0>2}:{s:0>2}!");
+    eprintln!("done in {h:0>2}:{m:0>2}:{s:0>2}!");
 
     Ok(())
 }
@@ -0,0 +1,97 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::{
+        palette::Palette,
+        structure::{
+            meta::SizeConstraint,
+            value::{parse_colour, parse_property_as_usize, Vusize},
+        },
+    },
+    utils::pipeline::ascii::{self, Ascii as AsciiStep, Ink},
+};
+
+/// Parses a ramp of characters, from the darkest to the brightest.
+pub fn parse_ramp(value: &Value, key: &str) -> Vec<char> {
+    let ramp: Vec<char> = value
+        .get("ramp")
+        .map(|v| {
+            v.as_str()
+                .unwrap_or_else(|| panic!("[{key}.ramp] must be a string."))
+        })
+        .unwrap_or(ascii::DEFAULT_RAMP)
+        .chars()
+        .collect();
+
+    if ramp.is_empty() {
+        panic!("[{key}.ramp] needs at least one character.");
+    }
+
+    ramp
+}
+
+#[derive(Debug)]
+/// Redraws the image as characters, picked from a `ramp` by how bright each cell is.
+pub struct Ascii {
+    grid: SizeConstraint,
+    ramp: Vec<char>,
+    invert: bool,
+    /// A fixed colour for the characters - otherwise they keep the image's colours.
+    ink: Option<[u8; 3]>,
+    background: [u8; 3],
+    palette: Option<Palette>,
+    scale: Vusize,
+}
+
+impl Ascii {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("ascii").unwrap();
+
+        let ramp = parse_ramp(value, "ascii");
+        if let Some(c) = ascii::unsupported(&ramp) {
+            unimplemented!("the built-in font can't draw {c:?} - only printable ASCII.");
+        }
+
+        let colour = |name: &str| {
+            value.get(name).map(|colour| {
+                let [r, g, b, _] = parse_colour(
+                    colour
+                        .as_str()
+                        .unwrap_or_else(|| panic!("[ascii.{name}] must be a string.")),
+                );
+                [r, g, b]
+            })
+        };
+
+        Self {
+            grid: value
+                .get("grid")
+                .and_then(SizeConstraint::from_value)
+                .unwrap_or_else(ascii::default_grid),
+            ramp,
+            invert: value
+                .get("invert")
+                .map(|v| v.as_bool().expect("[ascii.invert] must be a boolean."))
+                .unwrap_or(false),
+            ink: colour("ink"),
+            background: colour("background").unwrap_or([0, 0, 0]),
+            palette: value.get("palette").map(|_| Palette::from_value(value)),
+            scale: parse_property_as_usize(value, "scale").unwrap_or(1.into()),
+        }
+    }
+
+    pub fn generate(&self) -> AsciiStep {
+        AsciiStep {
+            grid: self.grid.clone(),
+            ramp: self.ramp.clone(),
+            invert: self.invert,
+            ink: match self.ink {
+                Some(ink) => Ink::Fixed(ink),
+                None => Ink::Source,
+            },
+            background: self.background,
+            palette: self.palette.as_ref().map(|palette| palette.generate()),
+            scale: self.scale.generate() as u32,
+        }
+    }
+}
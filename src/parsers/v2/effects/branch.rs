@@ -0,0 +1,48 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::effects::{EffectEntry, Effects},
+    utils::pipeline::Step,
+};
+
+#[derive(Debug)]
+/// A list of effects, grouped so they can be used as one - like as a single branch.
+pub struct Chain(Effects);
+
+impl Chain {
+    pub fn from_value(value: &Value) -> Self {
+        Self(Effects::from_sequence(
+            value
+                .get("chain")
+                .expect("[chain] needs a list of effects."),
+        ))
+    }
+
+    pub fn generate(&self) -> Step {
+        Step::Chain(self.0.generate())
+    }
+}
+
+#[derive(Debug)]
+/// Splits the image into several branches, each given the same image, then layers
+/// their results on top of it in order - each branch with its own blend, opacity and mask.
+pub struct Branch(Vec<EffectEntry>);
+
+impl Branch {
+    pub fn from_value(value: &Value, key: &str) -> Self {
+        Self(
+            value
+                .get(key)
+                .unwrap()
+                .as_sequence()
+                .unwrap_or_else(|| panic!("[{key}] must be a list of branches."))
+                .iter()
+                .map(EffectEntry::from_value)
+                .collect(),
+        )
+    }
+
+    pub fn generate(&self) -> Step {
+        Step::Branch(self.0.iter().filter_map(|entry| entry.generate()).collect())
+    }
+}
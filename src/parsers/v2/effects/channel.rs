@@ -0,0 +1,134 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{parse_property_as_f64, parse_property_as_usize, Vf64, Vusize},
+    utils::pipeline::channel::{ChannelOp, ChannelOps, ColourSpace},
+};
+
+#[derive(Debug)]
+enum ChannelOpConfig {
+    Add(Vf64),
+    Multiply(Vf64),
+    Clamp(Vf64, Vf64),
+    Curve(Vec<(Vf64, Vf64)>),
+    Quantize(Vusize),
+}
+
+impl ChannelOpConfig {
+    const OPS: [&'static str; 5] = ["add", "multiply", "clamp", "curve", "quantize"];
+
+    fn from_value(value: &Value) -> Self {
+        let ops: Vec<&str> = Self::OPS
+            .into_iter()
+            .filter(|op| value.get(op).is_some())
+            .collect();
+
+        match ops.as_slice() {
+            ["add"] => Self::Add(parse_property_as_f64(value, "add").unwrap()),
+            ["multiply"] => Self::Multiply(parse_property_as_f64(value, "multiply").unwrap()),
+            ["clamp"] => {
+                let clamp = value.get("clamp").unwrap();
+                Self::Clamp(
+                    parse_property_as_f64(clamp, "min").unwrap_or(0.0.into()),
+                    parse_property_as_f64(clamp, "max").unwrap_or(1.0.into()),
+                )
+            }
+            ["curve"] => Self::Curve(
+                value
+                    .get("curve")
+                    .unwrap()
+                    .as_sequence()
+                    .expect("[channel.curve] must be a list of points.")
+                    .iter()
+                    .map(|point| match point.as_sequence().map(Vec::as_slice) {
+                        Some([x, y]) => (Vf64::property(x), Vf64::property(y)),
+                        _ => panic!("[channel.curve] points must be written as [x, y]."),
+                    })
+                    .collect(),
+            ),
+            ["quantize"] => Self::Quantize(parse_property_as_usize(value, "quantize").unwrap()),
+            _ => panic!(
+                "each channel operation needs exactly one of {:?}.",
+                Self::OPS
+            ),
+        }
+    }
+
+    fn generate(&self) -> ChannelOp {
+        match self {
+            Self::Add(amount) => ChannelOp::Add(amount.generate() as f32),
+            Self::Multiply(factor) => ChannelOp::Multiply(factor.generate() as f32),
+            Self::Clamp(min, max) => ChannelOp::Clamp(min.generate() as f32, max.generate() as f32),
+            Self::Curve(points) => {
+                let mut points: Vec<(f32, f32)> = points
+                    .iter()
+                    .map(|(x, y)| (x.generate() as f32, y.generate() as f32))
+                    .collect();
+                points.sort_by(|a, b| a.0.total_cmp(&b.0));
+                ChannelOp::Curve(points)
+            }
+            Self::Quantize(levels) => ChannelOp::Quantize(levels.generate() as u32),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Works on the channels of another colour space - like quantizing lightness, but not hue.
+pub struct Channel {
+    space: ColourSpace,
+    ops: Vec<(usize, ChannelOpConfig)>,
+}
+
+impl Channel {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("channel").unwrap();
+
+        let space = value
+            .get("space")
+            .map(|v| v.as_str().expect("[channel.space] must be a string."))
+            .expect("[channel] needs a `space`.");
+        let space = match space {
+            "lch" => ColourSpace::Lch,
+            "oklab" => ColourSpace::Oklab,
+            "oklch" => ColourSpace::Oklch,
+            "hsv" => ColourSpace::Hsv,
+            "hsl" => ColourSpace::Hsl,
+            "ycbcr" => ColourSpace::YCbCr,
+            _ => unimplemented!("colour space {space} is not supported."),
+        };
+
+        let channels = space.channels();
+        let ops = value
+            .get("ops")
+            .and_then(Value::as_sequence)
+            .expect("[channel] needs a list of `ops`.")
+            .iter()
+            .map(|op| {
+                let (name, op) = op
+                    .as_mapping()
+                    .filter(|op| op.len() == 1)
+                    .and_then(|op| op.iter().next())
+                    .expect("[channel.ops] entries must be written as `<channel>: <operation>`.");
+                let name = name.as_str().unwrap_or_default();
+                let channel = channels.iter().position(|c| *c == name).unwrap_or_else(|| {
+                    panic!("{name} isn't a channel of {space:?} - use one of {channels:?}.")
+                });
+
+                (channel, ChannelOpConfig::from_value(op))
+            })
+            .collect();
+
+        Self { space, ops }
+    }
+
+    pub fn generate(&self) -> ChannelOps {
+        ChannelOps {
+            space: self.space,
+            ops: self
+                .ops
+                .iter()
+                .map(|(channel, op)| (*channel, op.generate()))
+                .collect(),
+        }
+    }
+}
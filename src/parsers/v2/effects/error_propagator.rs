@@ -5,7 +5,10 @@ use image_effects::dither::{
 };
 use serde_yaml::Value;
 
-use crate::parsers::v2::palette::Palette;
+use crate::parsers::v2::{
+    palette::Palette,
+    structure::value::{parse_property_as_f64, Vf64},
+};
 
 #[derive(Debug)]
 pub enum ErrorPropagatorKind {
@@ -38,6 +41,8 @@ impl ErrorPropagatorKind {
 pub struct ErrorPropagator {
     kind: ErrorPropagatorKind,
     palette: Palette,
+    /// Noise added before dithering, to break up banding.
+    noise: Option<Vf64>,
 }
 
 impl ErrorPropagator {
@@ -62,7 +67,15 @@ impl ErrorPropagator {
 
         let palette = Palette::from_value(value);
 
-        ErrorPropagator { kind, palette }
+        ErrorPropagator {
+            kind,
+            palette,
+            noise: parse_property_as_f64(value, "noise"),
+        }
+    }
+
+    pub fn noise(&self) -> Option<&Vf64> {
+        self.noise.as_ref()
     }
 
     pub fn generate(&self) -> error::ErrorPropagator<'static, 'static, WithPalette> {
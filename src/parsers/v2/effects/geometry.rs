@@ -0,0 +1,159 @@
+use image::Rgba;
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::{
+        meta::{Resample, SizeConstraint},
+        value::{parse_colour, parse_property_as_usize, ValueProperty, Vusize},
+    },
+    utils::pipeline::geometry::Geometry,
+};
+
+fn parse_filter(value: &Value) -> Resample {
+    value
+        .get("filter")
+        .map(Resample::from_value)
+        .unwrap_or(Resample::Nearest)
+}
+
+#[derive(Debug)]
+/// Resizes mid-pipeline, using any of the source's size constraints.
+pub struct Resize(SizeConstraint);
+
+impl Resize {
+    pub fn from_value(value: &Value) -> Self {
+        Self(
+            SizeConstraint::from_value(value.get("resize").unwrap())
+                .expect("[resize] needs a constraint, such as max-dim or exact."),
+        )
+    }
+
+    pub fn generate(&self) -> Geometry {
+        Geometry::Resize(self.0.clone())
+    }
+}
+
+#[derive(Debug)]
+/// Shrinks the image by a factor - pair with `upscale-to-source` for chunky pixels.
+pub struct DownscaleBy {
+    factor: Vusize,
+    filter: Resample,
+}
+
+impl DownscaleBy {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("downscale-by").unwrap();
+
+        Self {
+            factor: parse_property_as_usize(value, "factor")
+                .expect("[downscale-by] needs a `factor`."),
+            filter: parse_filter(value),
+        }
+    }
+
+    pub fn generate(&self) -> Geometry {
+        Geometry::DownscaleBy(self.factor.generate().max(1) as u32, self.filter)
+    }
+}
+
+#[derive(Debug)]
+/// Scales back up to the size the image had before any effects.
+pub struct UpscaleToSource {
+    filter: Resample,
+}
+
+impl UpscaleToSource {
+    pub fn from_value(value: &Value) -> Self {
+        Self {
+            filter: parse_filter(value.get("upscale-to-source").unwrap()),
+        }
+    }
+
+    pub fn generate(&self) -> Geometry {
+        Geometry::UpscaleToSource(self.filter)
+    }
+}
+
+#[derive(Debug)]
+pub struct Crop {
+    x: Option<Vusize>,
+    y: Option<Vusize>,
+    width: Vusize,
+    height: Vusize,
+}
+
+impl Crop {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("crop").unwrap();
+
+        // an empty image can't be saved, so every size has to be at least one pixel.
+        let size = |name: &str| {
+            let size = parse_property_as_usize(value, name)
+                .unwrap_or_else(|| panic!("[crop] needs a `{name}`."));
+            let zero = match &size {
+                ValueProperty::Fixed(size) => *size == 0,
+                ValueProperty::Choice(sizes) => sizes.contains(&0),
+                ValueProperty::Range(min, _) => *min == 0,
+            };
+            if zero {
+                panic!("[crop.{name}] must be at least 1.");
+            }
+            size
+        };
+
+        Self {
+            x: parse_property_as_usize(value, "x"),
+            y: parse_property_as_usize(value, "y"),
+            width: size("width"),
+            height: size("height"),
+        }
+    }
+
+    pub fn generate(&self) -> Geometry {
+        Geometry::Crop {
+            x: self.x.as_ref().map(|x| x.generate() as u32),
+            y: self.y.as_ref().map(|y| y.generate() as u32),
+            width: self.width.generate() as u32,
+            height: self.height.generate() as u32,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Adds a border - `all` sets every side, which the others then override.
+pub struct Pad {
+    top: Vusize,
+    right: Vusize,
+    bottom: Vusize,
+    left: Vusize,
+    colour: [u8; 4],
+}
+
+impl Pad {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("pad").unwrap();
+        let all = parse_property_as_usize(value, "all").unwrap_or(0.into());
+        let side = |name: &str| parse_property_as_usize(value, name).unwrap_or(all.clone());
+
+        Self {
+            top: side("top"),
+            right: side("right"),
+            bottom: side("bottom"),
+            left: side("left"),
+            colour: value
+                .get("colour")
+                .map(|v| parse_colour(v.as_str().expect("[pad.colour] must be a string.")))
+                .unwrap_or([0, 0, 0, 0]),
+        }
+    }
+
+    pub fn generate(&self) -> Geometry {
+        Geometry::Pad {
+            top: self.top.generate() as u32,
+            right: self.right.generate() as u32,
+            bottom: self.bottom.generate() as u32,
+            left: self.left.generate() as u32,
+            colour: Rgba(self.colour),
+        }
+    }
+}
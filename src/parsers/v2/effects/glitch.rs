@@ -0,0 +1,183 @@
+use rand::Rng;
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{
+        iteration_rng, parse_property_as_f64, parse_property_as_isize, parse_property_as_usize,
+        Vf64, Visize, Vusize,
+    },
+    utils::pipeline::glitch::{DatabendFormat, Glitch, SortKey},
+};
+
+fn parse_bool(value: &Value, effect: &str, name: &str, default: bool) -> bool {
+    value
+        .get(name)
+        .map(|v| {
+            v.as_bool()
+                .unwrap_or_else(|| panic!("[{effect}.{name}] must be a boolean."))
+        })
+        .unwrap_or(default)
+}
+
+#[derive(Debug)]
+pub struct PixelSort {
+    key: SortKey,
+    low: Vf64,
+    high: Vf64,
+    angle: Vf64,
+    reverse: bool,
+}
+
+impl PixelSort {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("pixel-sort").unwrap();
+
+        let key = match value
+            .get("by")
+            .map(|v| v.as_str().expect("[pixel-sort.by] must be a string."))
+        {
+            None | Some("luma") => SortKey::Luma,
+            Some("hue") => SortKey::Hue,
+            Some("saturation") => SortKey::Saturation,
+            Some(key) => unimplemented!("sorting by {key} is not supported."),
+        };
+
+        let angle = match value.get("direction").map(|v| {
+            v.as_str()
+                .expect("[pixel-sort.direction] must be a string.")
+        }) {
+            Some("row") => 0.0.into(),
+            Some("column") => 90.0.into(),
+            Some(direction) => unimplemented!(
+                "direction {direction} is not supported - use `angle` for anything else."
+            ),
+            None => parse_property_as_f64(value, "angle").unwrap_or(0.0.into()),
+        };
+
+        Self {
+            key,
+            low: parse_property_as_f64(value, "low").unwrap_or(0.25.into()),
+            high: parse_property_as_f64(value, "high").unwrap_or(0.8.into()),
+            angle,
+            reverse: parse_bool(value, "pixel-sort", "reverse", false),
+        }
+    }
+
+    pub fn generate(&self) -> Glitch {
+        Glitch::PixelSort {
+            key: self.key,
+            low: self.low.generate() as f32,
+            high: self.high.generate() as f32,
+            angle: self.angle.generate() as f32,
+            reverse: self.reverse,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Moves the `r`, `g` and `b` channels by their own `x` and `y` offsets.
+pub struct ChannelShift {
+    offsets: [(Visize, Visize); 3],
+    wrap: bool,
+}
+
+impl ChannelShift {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("channel-shift").unwrap();
+        let offset = |name: &str| match value.get(name) {
+            Some(channel) => (
+                parse_property_as_isize(channel, "x").unwrap_or(0.into()),
+                parse_property_as_isize(channel, "y").unwrap_or(0.into()),
+            ),
+            None => (0.into(), 0.into()),
+        };
+
+        Self {
+            offsets: [offset("r"), offset("g"), offset("b")],
+            wrap: parse_bool(value, "channel-shift", "wrap", true),
+        }
+    }
+
+    pub fn generate(&self) -> Glitch {
+        Glitch::ChannelShift {
+            offsets: self
+                .offsets
+                .each_ref()
+                .map(|(x, y)| (x.generate() as i32, y.generate() as i32)),
+            wrap: self.wrap,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScanlineDisplace {
+    probability: Vf64,
+    max_offset: Vusize,
+    height: Vusize,
+    wrap: bool,
+}
+
+impl ScanlineDisplace {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("scanline-displace").unwrap();
+
+        Self {
+            probability: parse_property_as_f64(value, "probability").unwrap_or(0.1.into()),
+            max_offset: parse_property_as_usize(value, "max-offset")
+                .expect("[scanline-displace] needs a `max-offset`."),
+            height: parse_property_as_usize(value, "height").unwrap_or(1.into()),
+            wrap: parse_bool(value, "scanline-displace", "wrap", true),
+        }
+    }
+
+    pub fn generate(&self) -> Glitch {
+        Glitch::ScanlineDisplace {
+            probability: self.probability.generate() as f32,
+            max_offset: self.max_offset.generate() as u32,
+            band: self.height.generate() as u32,
+            wrap: self.wrap,
+            seed: iteration_rng().random(),
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Corrupts `bytes` bytes of the image, encoded as a `jpeg` (at `quality`) or `bmp`.
+pub struct Databend {
+    format: String,
+    quality: Vusize,
+    bytes: Vusize,
+}
+
+impl Databend {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("databend").unwrap();
+
+        let format = value
+            .get("format")
+            .map(|v| v.as_str().expect("[databend.format] must be a string."))
+            .unwrap_or("jpeg");
+        if !["jpeg", "bmp"].contains(&format) {
+            unimplemented!("databending through {format} is not supported.");
+        }
+
+        Self {
+            format: format.to_string(),
+            quality: parse_property_as_usize(value, "quality").unwrap_or(75.into()),
+            bytes: parse_property_as_usize(value, "bytes").unwrap_or(10.into()),
+        }
+    }
+
+    pub fn generate(&self) -> Glitch {
+        let format = match self.format.as_str() {
+            "jpeg" => DatabendFormat::Jpeg(self.quality.generate().clamp(1, 100) as u8),
+            _ => DatabendFormat::Bmp,
+        };
+
+        Glitch::Databend {
+            format,
+            bytes: self.bytes.generate(),
+            seed: iteration_rng().random(),
+        }
+    }
+}
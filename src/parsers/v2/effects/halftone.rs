@@ -0,0 +1,127 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::{
+        palette::Palette,
+        structure::value::{
+            parse_colour, parse_property_as_f64, parse_property_as_usize, Vf64, Vusize,
+        },
+    },
+    utils::pipeline::halftone::{DotShape, Halftone as HalftoneStep, HalftoneMode, Screen},
+};
+
+#[derive(Debug)]
+enum ModeConfig {
+    Mono {
+        angle: Vf64,
+        ink: [f32; 3],
+        paper: [f32; 3],
+    },
+    /// The angles of the cyan, magenta, yellow and black screens.
+    Cmyk([Vf64; 4]),
+}
+
+#[derive(Debug)]
+/// A printed look, made of dots on rotated screens - either `mono` or `cmyk`.
+pub struct Halftone {
+    mode: ModeConfig,
+    cell_size: Vusize,
+    shape: DotShape,
+    palette: Option<Palette>,
+}
+
+impl Halftone {
+    /// The traditional screen angles for cyan, magenta, yellow and black.
+    const CMYK_ANGLES: [f64; 4] = [15.0, 75.0, 0.0, 45.0];
+    const CMYK_INKS: [[f32; 3]; 4] = [
+        [0.0, 1.0, 1.0],
+        [1.0, 0.0, 1.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0],
+    ];
+
+    fn parse_rgb(value: &Value, name: &str, default: [f32; 3]) -> [f32; 3] {
+        match value.get(name) {
+            Some(colour) => {
+                let [r, g, b, _] = parse_colour(
+                    colour
+                        .as_str()
+                        .unwrap_or_else(|| panic!("[halftone.{name}] must be a string.")),
+                );
+                [r, g, b].map(|c| c as f32 / 255.0)
+            }
+            None => default,
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("halftone").unwrap();
+
+        let mode = value
+            .get("mode")
+            .map(|v| v.as_str().expect("[halftone.mode] must be a string."))
+            .unwrap_or("mono");
+        let mode = match mode {
+            "mono" => ModeConfig::Mono {
+                angle: parse_property_as_f64(value, "angle").unwrap_or(45.0.into()),
+                ink: Self::parse_rgb(value, "ink", [0.0; 3]),
+                paper: Self::parse_rgb(value, "paper", [1.0; 3]),
+            },
+            "cmyk" => {
+                let angles = value.get("angles");
+                let angle = |i: usize, name: &str| {
+                    angles
+                        .and_then(|angles| parse_property_as_f64(angles, name))
+                        .unwrap_or(Self::CMYK_ANGLES[i].into())
+                };
+                ModeConfig::Cmyk([angle(0, "c"), angle(1, "m"), angle(2, "y"), angle(3, "k")])
+            }
+            _ => unimplemented!("halftone mode {mode} is not supported."),
+        };
+
+        let shape = value
+            .get("shape")
+            .map(|v| v.as_str().expect("[halftone.shape] must be a string."))
+            .unwrap_or("round");
+        let shape = match shape {
+            "round" => DotShape::Round,
+            "square" => DotShape::Square,
+            "line" => DotShape::Line,
+            "ellipse" => DotShape::Ellipse,
+            _ => unimplemented!("dot shape {shape} is not supported."),
+        };
+
+        Self {
+            mode,
+            cell_size: parse_property_as_usize(value, "cell-size").unwrap_or(8.into()),
+            shape,
+            palette: value.get("palette").map(|_| Palette::from_value(value)),
+        }
+    }
+
+    pub fn generate(&self) -> HalftoneStep {
+        let mode = match &self.mode {
+            ModeConfig::Mono { angle, ink, paper } => HalftoneMode::Mono {
+                screen: Screen {
+                    angle: angle.generate() as f32,
+                    ink: *ink,
+                },
+                paper: *paper,
+            },
+            ModeConfig::Cmyk(angles) => {
+                let mut screens = Self::CMYK_INKS.map(|ink| Screen { angle: 0.0, ink });
+                for (screen, angle) in screens.iter_mut().zip(angles) {
+                    screen.angle = angle.generate() as f32;
+                }
+                HalftoneMode::Cmyk(screens)
+            }
+        };
+
+        HalftoneStep {
+            mode,
+            cell_size: self.cell_size.generate() as u32,
+            shape: self.shape,
+            palette: self.palette.as_ref().map(|palette| palette.generate()),
+        }
+    }
+}
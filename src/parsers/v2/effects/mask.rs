@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use image::GrayImage;
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{parse_property_as_f64, Vf64},
+    utils::pipeline::mask::{Mask, MaskShape},
+};
+
+#[derive(Debug)]
+enum MaskShapeConfig {
+    Rect {
+        x: Vf64,
+        y: Vf64,
+        width: Vf64,
+        height: Vf64,
+    },
+    Ellipse {
+        cx: Vf64,
+        cy: Vf64,
+        rx: Vf64,
+        ry: Vf64,
+    },
+    Luma {
+        min: Vf64,
+        max: Vf64,
+    },
+    Hue {
+        min: Vf64,
+        max: Vf64,
+    },
+    /// Loaded once, up front, rather than every iteration.
+    Image(Arc<GrayImage>),
+}
+
+#[derive(Debug)]
+/// Limits an effect to part of the image - read from the `mask` key next to the effect.
+pub struct MaskConfig {
+    shape: MaskShapeConfig,
+    feather: Option<Vf64>,
+    invert: bool,
+}
+
+impl MaskConfig {
+    const SHAPES: [&'static str; 5] = ["rect", "ellipse", "luma", "hue", "image"];
+
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let mask = value.get("mask")?;
+
+        let shapes: Vec<&str> = Self::SHAPES
+            .into_iter()
+            .filter(|shape| mask.get(shape).is_some())
+            .collect();
+
+        let shape = match shapes.as_slice() {
+            [shape] => *shape,
+            [] => panic!("[mask] needs one of {:?}.", Self::SHAPES),
+            _ => panic!("[mask] can only have one of {:?}.", Self::SHAPES),
+        };
+
+        let inner = mask.get(shape).unwrap();
+        let property =
+            |name: &str, default: f64| parse_property_as_f64(inner, name).unwrap_or(default.into());
+
+        let shape = match shape {
+            "rect" => MaskShapeConfig::Rect {
+                x: property("x", 0.0),
+                y: property("y", 0.0),
+                width: property("width", 1.0),
+                height: property("height", 1.0),
+            },
+            "ellipse" => MaskShapeConfig::Ellipse {
+                cx: property("cx", 0.5),
+                cy: property("cy", 0.5),
+                rx: property("rx", 0.5),
+                ry: property("ry", 0.5),
+            },
+            "luma" => MaskShapeConfig::Luma {
+                min: property("min", 0.0),
+                max: property("max", 1.0),
+            },
+            "hue" => MaskShapeConfig::Hue {
+                min: property("min", 0.0),
+                max: property("max", 360.0),
+            },
+            "image" => {
+                let path = inner.as_str().expect("[mask.image] must be a path.");
+                let image = image::open(path)
+                    .unwrap_or_else(|e| panic!("[mask.image] couldn't open {path}: {e}"));
+                MaskShapeConfig::Image(Arc::new(image.to_luma8()))
+            }
+            _ => unreachable!(),
+        };
+
+        Some(Self {
+            shape,
+            feather: parse_property_as_f64(mask, "feather"),
+            invert: mask
+                .get("invert")
+                .map(|v| v.as_bool().expect("[mask.invert] must be a boolean."))
+                .unwrap_or(false),
+        })
+    }
+
+    pub fn generate(&self) -> Mask {
+        let shape = match &self.shape {
+            MaskShapeConfig::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => MaskShape::Rect {
+                x: x.generate(),
+                y: y.generate(),
+                width: width.generate(),
+                height: height.generate(),
+            },
+            MaskShapeConfig::Ellipse { cx, cy, rx, ry } => MaskShape::Ellipse {
+                cx: cx.generate(),
+                cy: cy.generate(),
+                rx: rx.generate(),
+                ry: ry.generate(),
+            },
+            MaskShapeConfig::Luma { min, max } => MaskShape::Luma {
+                min: min.generate(),
+                max: max.generate(),
+            },
+            MaskShapeConfig::Hue { min, max } => MaskShape::Hue {
+                min: min.generate(),
+                max: max.generate(),
+            },
+            MaskShapeConfig::Image(image) => MaskShape::Image(image.clone()),
+        };
+
+        Mask {
+            shape,
+            feather: self.feather.as_ref().map(|f| f.generate()).unwrap_or(0.0),
+            invert: self.invert,
+        }
+    }
+}
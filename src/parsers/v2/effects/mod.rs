@@ -1,27 +1,46 @@
-use image_effects::{
-    dispatch::{self, EffectEnum},
-    dither::{
-        self,
-        error::{self, WithPalette},
-    },
-    effect::Effect,
-    filter::filters,
-};
+use image::DynamicImage;
+use image_effects::dispatch::EffectEnum;
 use serde_yaml::Value;
 
-use crate::parsers::v2::effects::{
-    error_propagator::ErrorPropagator,
-    gradient_map::GradientMap,
-    ordered::Ordered,
-    quantize_hue::QuantizeHue,
-    simple::{Brighten, Contrast, HueRotate, MultiplyHue, Saturate},
+use crate::{
+    parsers::v2::effects::{
+        ascii::Ascii,
+        branch::{Branch, Chain},
+        channel::Channel,
+        error_propagator::ErrorPropagator,
+        geometry::{Crop, DownscaleBy, Pad, Resize, UpscaleToSource},
+        glitch::{ChannelShift, Databend, PixelSort, ScanlineDisplace},
+        gradient_map::GradientMap,
+        halftone::Halftone,
+        mask::MaskConfig,
+        noise::{FilmGrain, GaussianNoise, NoiseField, SaltAndPepper},
+        ordered::Ordered,
+        quantize_hue::QuantizeHue,
+        random::{OneOf, Repeat, Shuffle},
+        simple::{Brighten, Contrast, HueRotate, Invert, MultiplyHue, Saturate},
+        spatial::{BoxBlur, Convolve, EdgeDetect, Emboss, GaussianBlur, UnsharpMask},
+        tone::{Curves, Grayscale, Levels, Posterize, Threshold},
+    },
+    parsers::v2::structure::value::{parse_property_as_f64, Chance, VBlend, Vf64},
+    utils::pipeline::{blend::BlendMode, Layer, Pipeline, Step},
 };
 
+pub mod ascii;
+pub mod branch;
+pub mod channel;
 pub mod error_propagator;
+pub mod geometry;
+pub mod glitch;
 pub mod gradient_map;
+pub mod halftone;
+pub mod mask;
+pub mod noise;
 pub mod ordered;
 pub mod quantize_hue;
+pub mod random;
 pub mod simple;
+pub mod spatial;
+pub mod tone;
 
 #[derive(Debug)]
 pub enum EffectKind {
@@ -30,10 +49,43 @@ pub enum EffectKind {
     Contrast(Contrast),
     HueRotate(HueRotate),
     MultiplyHue(MultiplyHue),
+    Invert(Invert),
+    Grayscale(Grayscale),
+    Posterize(Posterize),
+    Threshold(Threshold),
+    Levels(Levels),
+    Curves(Curves),
+    GaussianBlur(GaussianBlur),
+    BoxBlur(BoxBlur),
+    UnsharpMask(UnsharpMask),
+    EdgeDetect(EdgeDetect),
+    Emboss(Emboss),
+    Convolve(Convolve),
+    PixelSort(PixelSort),
+    ChannelShift(ChannelShift),
+    ScanlineDisplace(ScanlineDisplace),
+    Databend(Databend),
+    Channel(Channel),
+    Halftone(Halftone),
+    Ascii(Ascii),
+    FilmGrain(FilmGrain),
+    GaussianNoise(GaussianNoise),
+    SaltAndPepper(SaltAndPepper),
+    NoiseField(NoiseField),
     QuantizeHue(QuantizeHue),
     GradientMap(GradientMap),
     ErrorPropagator(ErrorPropagator),
     Ordered(Ordered),
+    Resize(Resize),
+    DownscaleBy(DownscaleBy),
+    UpscaleToSource(UpscaleToSource),
+    Crop(Crop),
+    Pad(Pad),
+    Chain(Chain),
+    Branch(Branch),
+    OneOf(OneOf),
+    Shuffle(Shuffle),
+    Repeat(Repeat),
 }
 
 impl From<Brighten> for EffectKind {
@@ -66,6 +118,144 @@ impl From<MultiplyHue> for EffectKind {
     }
 }
 
+impl From<Invert> for EffectKind {
+    fn from(value: Invert) -> Self {
+        Self::Invert(value)
+    }
+}
+
+impl From<Grayscale> for EffectKind {
+    fn from(value: Grayscale) -> Self {
+        Self::Grayscale(value)
+    }
+}
+
+impl From<Posterize> for EffectKind {
+    fn from(value: Posterize) -> Self {
+        Self::Posterize(value)
+    }
+}
+
+impl From<Threshold> for EffectKind {
+    fn from(value: Threshold) -> Self {
+        Self::Threshold(value)
+    }
+}
+
+impl From<Levels> for EffectKind {
+    fn from(value: Levels) -> Self {
+        Self::Levels(value)
+    }
+}
+
+impl From<Curves> for EffectKind {
+    fn from(value: Curves) -> Self {
+        Self::Curves(value)
+    }
+}
+
+impl From<GaussianBlur> for EffectKind {
+    fn from(value: GaussianBlur) -> Self {
+        Self::GaussianBlur(value)
+    }
+}
+
+impl From<BoxBlur> for EffectKind {
+    fn from(value: BoxBlur) -> Self {
+        Self::BoxBlur(value)
+    }
+}
+
+impl From<UnsharpMask> for EffectKind {
+    fn from(value: UnsharpMask) -> Self {
+        Self::UnsharpMask(value)
+    }
+}
+
+impl From<EdgeDetect> for EffectKind {
+    fn from(value: EdgeDetect) -> Self {
+        Self::EdgeDetect(value)
+    }
+}
+
+impl From<Emboss> for EffectKind {
+    fn from(value: Emboss) -> Self {
+        Self::Emboss(value)
+    }
+}
+
+impl From<Convolve> for EffectKind {
+    fn from(value: Convolve) -> Self {
+        Self::Convolve(value)
+    }
+}
+
+impl From<PixelSort> for EffectKind {
+    fn from(value: PixelSort) -> Self {
+        Self::PixelSort(value)
+    }
+}
+
+impl From<ChannelShift> for EffectKind {
+    fn from(value: ChannelShift) -> Self {
+        Self::ChannelShift(value)
+    }
+}
+
+impl From<ScanlineDisplace> for EffectKind {
+    fn from(value: ScanlineDisplace) -> Self {
+        Self::ScanlineDisplace(value)
+    }
+}
+
+impl From<Databend> for EffectKind {
+    fn from(value: Databend) -> Self {
+        Self::Databend(value)
+    }
+}
+
+impl From<Channel> for EffectKind {
+    fn from(value: Channel) -> Self {
+        Self::Channel(value)
+    }
+}
+
+impl From<Halftone> for EffectKind {
+    fn from(value: Halftone) -> Self {
+        Self::Halftone(value)
+    }
+}
+
+impl From<Ascii> for EffectKind {
+    fn from(value: Ascii) -> Self {
+        Self::Ascii(value)
+    }
+}
+
+impl From<FilmGrain> for EffectKind {
+    fn from(value: FilmGrain) -> Self {
+        Self::FilmGrain(value)
+    }
+}
+
+impl From<GaussianNoise> for EffectKind {
+    fn from(value: GaussianNoise) -> Self {
+        Self::GaussianNoise(value)
+    }
+}
+
+impl From<SaltAndPepper> for EffectKind {
+    fn from(value: SaltAndPepper) -> Self {
+        Self::SaltAndPepper(value)
+    }
+}
+
+impl From<NoiseField> for EffectKind {
+    fn from(value: NoiseField) -> Self {
+        Self::NoiseField(value)
+    }
+}
+
 impl From<QuantizeHue> for EffectKind {
     fn from(value: QuantizeHue) -> Self {
         Self::QuantizeHue(value)
@@ -90,16 +280,75 @@ impl From<Ordered> for EffectKind {
     }
 }
 
+impl From<Resize> for EffectKind {
+    fn from(value: Resize) -> Self {
+        Self::Resize(value)
+    }
+}
+
+impl From<DownscaleBy> for EffectKind {
+    fn from(value: DownscaleBy) -> Self {
+        Self::DownscaleBy(value)
+    }
+}
+
+impl From<UpscaleToSource> for EffectKind {
+    fn from(value: UpscaleToSource) -> Self {
+        Self::UpscaleToSource(value)
+    }
+}
+
+impl From<Crop> for EffectKind {
+    fn from(value: Crop) -> Self {
+        Self::Crop(value)
+    }
+}
+
+impl From<Pad> for EffectKind {
+    fn from(value: Pad) -> Self {
+        Self::Pad(value)
+    }
+}
+
+impl From<Chain> for EffectKind {
+    fn from(value: Chain) -> Self {
+        Self::Chain(value)
+    }
+}
+
+impl From<Branch> for EffectKind {
+    fn from(value: Branch) -> Self {
+        Self::Branch(value)
+    }
+}
+
+impl From<OneOf> for EffectKind {
+    fn from(value: OneOf) -> Self {
+        Self::OneOf(value)
+    }
+}
+
+impl From<Shuffle> for EffectKind {
+    fn from(value: Shuffle) -> Self {
+        Self::Shuffle(value)
+    }
+}
+
+impl From<Repeat> for EffectKind {
+    fn from(value: Repeat) -> Self {
+        Self::Repeat(value)
+    }
+}
+
 impl EffectKind {
     pub fn from_value(value: &Value) -> Self {
         let effect = value
             .as_mapping()
             .unwrap()
             .keys()
-            .next()
-            .unwrap()
-            .as_str()
-            .unwrap();
+            .filter_map(|key| key.as_str())
+            .find(|key| !EffectEntry::OPTIONS.contains(key))
+            .expect("effect entries need an effect.");
 
         match effect {
             "brighten" => Brighten::from_value(value).into(),
@@ -107,76 +356,257 @@ impl EffectKind {
             "contrast" => Contrast::from_value(value).into(),
             "hue-rotate" => HueRotate::from_value(value).into(),
             "multiply-hue" => MultiplyHue::from_value(value).into(),
+            "invert" => Invert::from_value(value).into(),
+            "grayscale" => Grayscale::from_value(value).into(),
+            "posterize" => Posterize::from_value(value).into(),
+            "threshold" => Threshold::from_value(value).into(),
+            "levels" => Levels::from_value(value).into(),
+            "curves" => Curves::from_value(value).into(),
+            "gaussian-blur" => GaussianBlur::from_value(value).into(),
+            "box-blur" => BoxBlur::from_value(value).into(),
+            "unsharp-mask" => UnsharpMask::from_value(value).into(),
+            "edge-detect" | "sobel" => EdgeDetect::from_value(value).into(),
+            "emboss" => Emboss::from_value(value).into(),
+            "convolve" => Convolve::from_value(value).into(),
+            "pixel-sort" => PixelSort::from_value(value).into(),
+            "channel-shift" => ChannelShift::from_value(value).into(),
+            "scanline-displace" => ScanlineDisplace::from_value(value).into(),
+            "databend" => Databend::from_value(value).into(),
+            "channel" => Channel::from_value(value).into(),
+            "halftone" => Halftone::from_value(value).into(),
+            "ascii" => Ascii::from_value(value).into(),
+            "film-grain" => FilmGrain::from_value(value).into(),
+            "gaussian-noise" => GaussianNoise::from_value(value).into(),
+            "salt-and-pepper" => SaltAndPepper::from_value(value).into(),
+            "perlin" | "simplex" => NoiseField::from_value(value, effect).into(),
             "quantize-hue" => QuantizeHue::from_value(value).into(),
             "gradient-map" => GradientMap::from_value(value).into(),
             "error-propagator" => ErrorPropagator::from_value(value).into(),
             "ordered" => Ordered::from_value(value).into(),
+            "resize" => Resize::from_value(value).into(),
+            "downscale-by" => DownscaleBy::from_value(value).into(),
+            "upscale-to-source" => UpscaleToSource::from_value(value).into(),
+            "crop" => Crop::from_value(value).into(),
+            "pad" => Pad::from_value(value).into(),
+            "chain" => Chain::from_value(value).into(),
+            "branch" | "layers" => Branch::from_value(value, effect).into(),
+            "one-of" => OneOf::from_value(value).into(),
+            "shuffle" => Shuffle::from_value(value).into(),
+            "repeat" => Repeat::from_value(value).into(),
             _ => unimplemented!("effect {effect} is not supported."),
         }
     }
 
-    pub fn generate<'a, 'b, T>(&self) -> EffectEnum<T>
-    where
-        dispatch::Null<T>: Effect<T>,
-        filters::Invert: Effect<T>,
-        filters::HueRotate: Effect<T>,
-        filters::Contrast: Effect<T>,
-        filters::Brighten: Effect<T>,
-        filters::Saturate: Effect<T>,
-        filters::GradientMap: Effect<T>,
-        filters::QuantizeHue: Effect<T>,
-        filters::MultiplyHue: Effect<T>,
-        dither::ordered::Ordered: Effect<T>,
-        error::ErrorPropagator<'static, 'static, WithPalette>: Effect<T>,
-    {
+    /// The name this effect goes by in configuration files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Brighten(_) => "brighten",
+            Self::Saturate(_) => "saturate",
+            Self::Contrast(_) => "contrast",
+            Self::HueRotate(_) => "hue-rotate",
+            Self::MultiplyHue(_) => "multiply-hue",
+            Self::Invert(_) => "invert",
+            Self::Grayscale(_) => "grayscale",
+            Self::Posterize(_) => "posterize",
+            Self::Threshold(_) => "threshold",
+            Self::Levels(_) => "levels",
+            Self::Curves(_) => "curves",
+            Self::GaussianBlur(_) => "gaussian-blur",
+            Self::BoxBlur(_) => "box-blur",
+            Self::UnsharpMask(_) => "unsharp-mask",
+            Self::EdgeDetect(_) => "edge-detect",
+            Self::Emboss(_) => "emboss",
+            Self::Convolve(_) => "convolve",
+            Self::PixelSort(_) => "pixel-sort",
+            Self::ChannelShift(_) => "channel-shift",
+            Self::ScanlineDisplace(_) => "scanline-displace",
+            Self::Databend(_) => "databend",
+            Self::Channel(_) => "channel",
+            Self::Halftone(_) => "halftone",
+            Self::Ascii(_) => "ascii",
+            Self::FilmGrain(_) => "film-grain",
+            Self::GaussianNoise(_) => "gaussian-noise",
+            Self::SaltAndPepper(_) => "salt-and-pepper",
+            Self::NoiseField(f) => f.name(),
+            Self::QuantizeHue(_) => "quantize-hue",
+            Self::GradientMap(_) => "gradient-map",
+            Self::ErrorPropagator(_) => "error-propagator",
+            Self::Ordered(_) => "ordered",
+            Self::Resize(_) => "resize",
+            Self::DownscaleBy(_) => "downscale-by",
+            Self::UpscaleToSource(_) => "upscale-to-source",
+            Self::Crop(_) => "crop",
+            Self::Pad(_) => "pad",
+            Self::Chain(_) => "chain",
+            Self::Branch(_) => "branch",
+            Self::OneOf(_) => "one-of",
+            Self::Shuffle(_) => "shuffle",
+            Self::Repeat(_) => "repeat",
+        }
+    }
+
+    pub fn generate(&self) -> Step {
+        match self {
+            Self::Brighten(f) => Self::effect(f.generate()),
+            Self::Saturate(f) => Self::effect(f.generate()),
+            Self::Contrast(f) => Self::effect(f.generate()),
+            Self::HueRotate(f) => Self::effect(f.generate()),
+            Self::MultiplyHue(f) => Self::effect(f.generate()),
+            Self::Invert(f) => Self::effect(f.generate()),
+            Self::Grayscale(f) => f.generate().into(),
+            Self::Posterize(f) => f.generate().into(),
+            Self::Threshold(f) => f.generate().into(),
+            Self::Levels(f) => f.generate().into(),
+            Self::Curves(f) => f.generate().into(),
+            Self::GaussianBlur(f) => f.generate().into(),
+            Self::BoxBlur(f) => f.generate().into(),
+            Self::UnsharpMask(f) => f.generate().into(),
+            Self::EdgeDetect(f) => f.generate().into(),
+            Self::Emboss(f) => f.generate().into(),
+            Self::Convolve(f) => f.generate().into(),
+            Self::PixelSort(f) => f.generate().into(),
+            Self::ChannelShift(f) => f.generate().into(),
+            Self::ScanlineDisplace(f) => f.generate().into(),
+            Self::Databend(f) => f.generate().into(),
+            Self::Channel(f) => f.generate().into(),
+            Self::Halftone(f) => f.generate().into(),
+            Self::Ascii(f) => f.generate().into(),
+            Self::FilmGrain(f) => f.generate().into(),
+            Self::GaussianNoise(f) => f.generate().into(),
+            Self::SaltAndPepper(f) => f.generate().into(),
+            Self::NoiseField(f) => f.generate().into(),
+            Self::QuantizeHue(f) => Self::effect(f.generate()),
+            Self::GradientMap(f) => Self::effect(f.generate()),
+            Self::ErrorPropagator(f) => noise::before(f.noise(), Self::effect(f.generate())),
+            Self::Ordered(f) => noise::before(f.noise(), Self::effect(f.generate_effect())),
+            Self::Resize(f) => f.generate().into(),
+            Self::DownscaleBy(f) => f.generate().into(),
+            Self::UpscaleToSource(f) => f.generate().into(),
+            Self::Crop(f) => f.generate().into(),
+            Self::Pad(f) => f.generate().into(),
+            Self::Chain(f) => f.generate(),
+            Self::Branch(f) => f.generate(),
+            Self::OneOf(f) => f.generate(),
+            Self::Shuffle(f) => f.generate(),
+            Self::Repeat(f) => f.generate(),
+        }
+    }
+
+    /// Whether this only holds other effects, rather than being one itself.
+    fn is_group(&self) -> bool {
+        matches!(
+            self,
+            Self::Chain(_) | Self::Branch(_) | Self::OneOf(_) | Self::Shuffle(_) | Self::Repeat(_)
+        )
+    }
+
+    /// The blend mode used when the entry doesn't give one.
+    fn default_blend(&self) -> BlendMode {
         match self {
-            Self::Brighten(f) => f.generate().into(),
-            Self::Saturate(f) => f.generate().into(),
-            Self::Contrast(f) => f.generate().into(),
-            Self::HueRotate(f) => f.generate().into(),
-            Self::MultiplyHue(f) => f.generate().into(),
-            Self::QuantizeHue(f) => f.generate().into(),
-            Self::GradientMap(f) => f.generate().into(),
-            Self::ErrorPropagator(f) => f.generate().into(),
-            Self::Ordered(f) => f.generate_effect().into(),
+            Self::NoiseField(_) => BlendMode::Overlay,
+            _ => BlendMode::Normal,
+        }
+    }
+
+    /// Wraps one of `image_effects`' effects as a step.
+    fn effect(effect: impl Into<EffectEnum<DynamicImage>>) -> Step {
+        Step::Effect(effect.into())
+    }
+}
+
+#[derive(Debug)]
+/// An effect, along with the options that apply to any effect.
+pub struct EffectEntry {
+    kind: EffectKind,
+    mask: Option<MaskConfig>,
+    blend: Option<VBlend>,
+    opacity: Option<Vf64>,
+    /// How likely the effect is to be used at all.
+    chance: Option<Chance>,
+    /// How likely the effect is to be picked, compared to the rest of a `one-of`.
+    weight: f64,
+}
+
+impl EffectEntry {
+    /// Keys that can sit next to any effect, rather than being the effect itself.
+    const OPTIONS: [&'static str; 5] = ["mask", "blend", "opacity", "chance", "weight"];
+
+    pub fn from_value(value: &Value) -> Self {
+        Self {
+            kind: EffectKind::from_value(value),
+            mask: MaskConfig::from_value(value),
+            blend: value.get("blend").map(VBlend::property),
+            opacity: parse_property_as_f64(value, "opacity"),
+            chance: parse_property_as_f64(value, "chance").map(Chance::from),
+            weight: value
+                .get("weight")
+                .map(|v| v.as_f64().expect("[weight] must be a number."))
+                .unwrap_or(1.0),
+        }
+    }
+
+    /// Generates the effect, unless its `chance` says to skip it this time.
+    pub fn generate(&self) -> Option<Layer> {
+        if let Some(chance) = &self.chance {
+            if !chance.happens() {
+                return None;
+            }
         }
+
+        Some(Layer {
+            step: self.kind.generate(),
+            name: (!self.kind.is_group()).then(|| self.kind.name()),
+            mask: self.mask.as_ref().map(|mask| mask.generate()),
+            blend: self
+                .blend
+                .as_ref()
+                .map(|blend| blend.generate())
+                .unwrap_or(self.kind.default_blend()),
+            opacity: self.opacity.as_ref().map(|o| o.generate()).unwrap_or(1.0),
+        })
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.weight
     }
 }
 
 #[derive(Debug)]
 pub struct Effects {
-    kinds: Vec<EffectKind>,
+    entries: Vec<EffectEntry>,
 }
 
 impl Effects {
     pub fn from_value(value: &Value) -> Self {
+        Self::from_sequence(value.get("effects").unwrap())
+    }
+
+    /// Reads a list of effect entries, like the top-level `effects`.
+    pub fn from_sequence(value: &Value) -> Self {
         Self {
-            kinds: value
-                .get("effects")
-                .unwrap()
+            entries: value
                 .as_sequence()
                 .unwrap()
                 .iter()
-                .map(|s| EffectKind::from_value(s))
+                .map(|s| EffectEntry::from_value(s))
                 .collect(),
         }
     }
 
-    pub fn generate<T>(&self) -> Vec<EffectEnum<T>>
-    where
-        dispatch::Null<T>: Effect<T>,
-        filters::Invert: Effect<T>,
-        filters::HueRotate: Effect<T>,
-        filters::Contrast: Effect<T>,
-        filters::Brighten: Effect<T>,
-        filters::Saturate: Effect<T>,
-        filters::GradientMap: Effect<T>,
-        filters::QuantizeHue: Effect<T>,
-        filters::MultiplyHue: Effect<T>,
-        dither::ordered::Ordered: Effect<T>,
-        error::ErrorPropagator<'static, 'static, WithPalette>: Effect<T>,
-    {
-        self.kinds.iter().map(|kind| kind.generate()).collect()
+    pub fn entries(&self) -> &[EffectEntry] {
+        &self.entries
+    }
+
+    /// Generates every effect that isn't skipped, in order.
+    pub fn layers(&self) -> Vec<Layer> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.generate())
+            .collect()
+    }
+
+    pub fn generate(&self) -> Pipeline {
+        Pipeline::new(self.layers())
     }
 }
 
@@ -214,3 +644,29 @@ impl Effects {
 //         }
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_come_from_what_was_generated() {
+        let value: Value = serde_yaml::from_str(
+            "
+            effects:
+              - brighten: { factor: 0.1 }
+              - contrast: { factor: 1.2 }
+                chance: 0.0
+              - one-of:
+                  - chain:
+                      - saturate: { factor: 0.5 }
+                      - hue-rotate: { factor: 30 }
+            ",
+        )
+        .unwrap();
+
+        let pipeline = Effects::from_value(&value).generate();
+
+        assert_eq!(pipeline.names(), ["brighten", "saturate", "hue-rotate"]);
+    }
+}
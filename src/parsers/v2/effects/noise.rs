@@ -0,0 +1,166 @@
+use rand::Rng;
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{
+        iteration_rng, parse_property_as_f64, parse_property_as_usize, Vf64, Vusize,
+    },
+    utils::pipeline::{
+        noise::{Noise, NoiseKind},
+        Layer, Pipeline, Step,
+    },
+};
+
+fn noise(kind: NoiseKind) -> Noise {
+    Noise {
+        kind,
+        seed: iteration_rng().random(),
+    }
+}
+
+/// Adds gaussian noise of `amount` (if there is any) to the image before `step` - handy
+/// for breaking up banding before a dither.
+pub fn before(amount: Option<&Vf64>, step: Step) -> Step {
+    match amount {
+        Some(amount) => {
+            let noise = noise(NoiseKind::Gaussian {
+                amount: amount.generate() as f32,
+                mono: true,
+            });
+            Step::Chain(Pipeline::new(vec![
+                Layer::from(Step::from(noise)),
+                step.into(),
+            ]))
+        }
+        None => step,
+    }
+}
+
+#[derive(Debug)]
+/// Grain that only changes brightness, in clumps about `size` pixels across.
+pub struct FilmGrain {
+    amount: Vf64,
+    size: Vf64,
+}
+
+impl FilmGrain {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("film-grain").unwrap();
+
+        Self {
+            amount: parse_property_as_f64(value, "amount").unwrap_or(0.08.into()),
+            size: parse_property_as_f64(value, "size").unwrap_or(1.5.into()),
+        }
+    }
+
+    pub fn generate(&self) -> Noise {
+        noise(NoiseKind::FilmGrain {
+            amount: self.amount.generate() as f32,
+            size: self.size.generate() as f32,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct GaussianNoise {
+    amount: Vf64,
+    mono: bool,
+}
+
+impl GaussianNoise {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("gaussian-noise").unwrap();
+
+        Self {
+            amount: parse_property_as_f64(value, "amount").unwrap_or(0.05.into()),
+            mono: value
+                .get("mono")
+                .map(|v| {
+                    v.as_bool()
+                        .expect("[gaussian-noise.mono] must be a boolean.")
+                })
+                .unwrap_or(false),
+        }
+    }
+
+    pub fn generate(&self) -> Noise {
+        noise(NoiseKind::Gaussian {
+            amount: self.amount.generate() as f32,
+            mono: self.mono,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct SaltAndPepper {
+    amount: Vf64,
+}
+
+impl SaltAndPepper {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("salt-and-pepper").unwrap();
+
+        Self {
+            amount: parse_property_as_f64(value, "amount").unwrap_or(0.02.into()),
+        }
+    }
+
+    pub fn generate(&self) -> Noise {
+        noise(NoiseKind::SaltAndPepper {
+            amount: self.amount.generate() as f32,
+        })
+    }
+}
+
+#[derive(Debug)]
+/// A `perlin` or `simplex` noise field, blended over the image - with `overlay`, unless
+/// the effect says otherwise.
+pub struct NoiseField {
+    simplex: bool,
+    scale: Vf64,
+    octaves: Vusize,
+    amount: Vf64,
+}
+
+impl NoiseField {
+    pub fn from_value(value: &Value, key: &str) -> Self {
+        let value = value.get(key).unwrap();
+
+        Self {
+            simplex: key == "simplex",
+            scale: parse_property_as_f64(value, "scale").unwrap_or(64.0.into()),
+            octaves: parse_property_as_usize(value, "octaves").unwrap_or(4.into()),
+            amount: parse_property_as_f64(value, "amount").unwrap_or(0.5.into()),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.simplex {
+            "simplex"
+        } else {
+            "perlin"
+        }
+    }
+
+    pub fn generate(&self) -> Noise {
+        let (scale, octaves, amount) = (
+            self.scale.generate() as f32,
+            self.octaves.generate() as u32,
+            self.amount.generate() as f32,
+        );
+
+        noise(if self.simplex {
+            NoiseKind::Simplex {
+                scale,
+                octaves,
+                amount,
+            }
+        } else {
+            NoiseKind::Perlin {
+                scale,
+                octaves,
+                amount,
+            }
+        })
+    }
+}
@@ -13,6 +13,7 @@ use crate::parsers::v2::{
         strategies::Effect,
     },
     palette::{self, Palette},
+    structure::value::{iteration_rng, parse_property_as_f64, Vf64},
 };
 
 #[derive(Debug)]
@@ -28,6 +29,8 @@ pub struct Ordered {
     invert: Option<Invert>,
     mirror: Option<Mirror>,
     palette: palette::Palette,
+    /// Noise added before dithering, to break up banding.
+    noise: Option<Vf64>,
 }
 
 impl Ordered {
@@ -50,13 +53,18 @@ impl Ordered {
             invert: Invert::from_value(value),
             mirror: Mirror::from_value(value),
             palette: Palette::from_value(value),
+            noise: parse_property_as_f64(value, "noise"),
         }
     }
 
+    pub fn noise(&self) -> Option<&Vf64> {
+        self.noise.as_ref()
+    }
+
     pub fn generate_effect(&self) -> ordered::Ordered {
         let mut strategy: OrderedStrategy = self
             .strategies
-            .choose(&mut rand::rng())
+            .choose(&mut iteration_rng())
             .unwrap()
             .generate_effect();
 
@@ -0,0 +1,90 @@
+use rand::{seq::SliceRandom, Rng};
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::{
+        effects::Effects,
+        structure::value::{iteration_rng, parse_property_as_usize, Vusize},
+    },
+    utils::pipeline::{Pipeline, Step},
+};
+
+#[derive(Debug)]
+/// Picks one of its effects each iteration, going by each one's `weight`.
+pub struct OneOf(Effects);
+
+impl OneOf {
+    pub fn from_value(value: &Value) -> Self {
+        let effects = Effects::from_sequence(value.get("one-of").unwrap());
+
+        if effects.entries().is_empty() {
+            panic!("[one-of] needs at least one effect.");
+        }
+        if effects.entries().iter().any(|entry| entry.weight() < 0.0) {
+            panic!("[one-of] weights can't be negative.");
+        }
+
+        Self(effects)
+    }
+
+    pub fn generate(&self) -> Step {
+        let entries = self.0.entries();
+        let total: f64 = entries.iter().map(|entry| entry.weight()).sum();
+        let mut pick = iteration_rng().random_range(0.0..=total);
+
+        let picked = entries
+            .iter()
+            .find(|entry| {
+                pick -= entry.weight();
+                pick <= 0.0
+            })
+            .unwrap_or(entries.last().unwrap());
+
+        Step::Chain(Pipeline::new(picked.generate().into_iter().collect()))
+    }
+}
+
+#[derive(Debug)]
+/// Applies its effects in a random order each iteration.
+pub struct Shuffle(Effects);
+
+impl Shuffle {
+    pub fn from_value(value: &Value) -> Self {
+        Self(Effects::from_sequence(value.get("shuffle").unwrap()))
+    }
+
+    pub fn generate(&self) -> Step {
+        let mut layers = self.0.layers();
+        layers.shuffle(&mut iteration_rng());
+        Step::Chain(Pipeline::new(layers))
+    }
+}
+
+#[derive(Debug)]
+/// Applies its effects several times over, generating them anew each time.
+pub struct Repeat {
+    times: Vusize,
+    effects: Effects,
+}
+
+impl Repeat {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("repeat").unwrap();
+
+        Self {
+            times: parse_property_as_usize(value, "times").expect("[repeat] needs `times`."),
+            effects: Effects::from_sequence(
+                value
+                    .get("effects")
+                    .expect("[repeat] needs a list of `effects`."),
+            ),
+        }
+    }
+
+    pub fn generate(&self) -> Step {
+        let times = self.times.generate();
+        Step::Chain(Pipeline::new(
+            (0..times).flat_map(|_| self.effects.layers()).collect(),
+        ))
+    }
+}
@@ -71,3 +71,17 @@ impl MultiplyHue {
         filters::MultiplyHue(self.0.generate() as f32)
     }
 }
+
+#[derive(Debug)]
+/// Represents an invert effect - it takes no options, so write it as `invert: {}`.
+pub struct Invert;
+
+impl Invert {
+    pub fn from_value(_value: &Value) -> Self {
+        Self
+    }
+
+    pub fn generate(&self) -> filters::Invert {
+        filters::Invert
+    }
+}
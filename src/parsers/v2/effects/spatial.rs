@@ -0,0 +1,142 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{parse_property_as_f64, parse_property_as_usize, Vf64, Vusize},
+    utils::pipeline::spatial::{Kernel, Spatial},
+};
+
+#[derive(Debug)]
+pub struct GaussianBlur(Vf64);
+
+impl GaussianBlur {
+    pub fn from_value(value: &Value) -> Self {
+        Self(
+            parse_property_as_f64(value.get("gaussian-blur").unwrap(), "sigma")
+                .expect("[gaussian-blur] needs a `sigma`."),
+        )
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::GaussianBlur(self.0.generate() as f32)
+    }
+}
+
+#[derive(Debug)]
+pub struct BoxBlur(Vusize);
+
+impl BoxBlur {
+    pub fn from_value(value: &Value) -> Self {
+        Self(
+            parse_property_as_usize(value.get("box-blur").unwrap(), "radius")
+                .expect("[box-blur] needs a `radius`."),
+        )
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::BoxBlur(self.0.generate() as u32)
+    }
+}
+
+#[derive(Debug)]
+/// Sharpens by exaggerating the difference from a blurred copy.
+pub struct UnsharpMask {
+    sigma: Vf64,
+    amount: Vf64,
+    threshold: Vf64,
+}
+
+impl UnsharpMask {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("unsharp-mask").unwrap();
+
+        Self {
+            sigma: parse_property_as_f64(value, "sigma").unwrap_or(1.0.into()),
+            amount: parse_property_as_f64(value, "amount").unwrap_or(1.0.into()),
+            threshold: parse_property_as_f64(value, "threshold").unwrap_or(0.0.into()),
+        }
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::UnsharpMask {
+            sigma: self.sigma.generate() as f32,
+            amount: self.amount.generate() as f32,
+            threshold: self.threshold.generate() as f32,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Finds edges with the Sobel operator - it takes no options, so write it as `sobel: {}`.
+pub struct EdgeDetect;
+
+impl EdgeDetect {
+    pub fn from_value(_value: &Value) -> Self {
+        Self
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::EdgeDetect
+    }
+}
+
+#[derive(Debug)]
+pub struct Emboss(Vf64);
+
+impl Emboss {
+    pub fn from_value(value: &Value) -> Self {
+        Self(parse_property_as_f64(value.get("emboss").unwrap(), "strength").unwrap_or(1.0.into()))
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::Emboss(self.0.generate() as f32)
+    }
+}
+
+#[derive(Debug)]
+/// Runs a kernel written as a list of rows, divided by `divisor` (or the sum of the
+/// weights), with `bias` added on top.
+pub struct Convolve {
+    rows: Vec<Vec<Vf64>>,
+    divisor: Option<Vf64>,
+    bias: Option<Vf64>,
+}
+
+impl Convolve {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("convolve").unwrap();
+
+        let rows = value
+            .get("kernel")
+            .and_then(Value::as_sequence)
+            .expect("[convolve] needs a `kernel`, as a list of rows.")
+            .iter()
+            .map(|row| {
+                row.as_sequence()
+                    .expect("[convolve.kernel] rows must be lists of weights.")
+                    .iter()
+                    .map(Vf64::property)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            rows,
+            divisor: parse_property_as_f64(value, "divisor"),
+            bias: parse_property_as_f64(value, "bias"),
+        }
+    }
+
+    pub fn generate(&self) -> Spatial {
+        Spatial::Convolve(Kernel::new(
+            self.rows
+                .iter()
+                .map(|row| row.iter().map(|w| w.generate() as f32).collect())
+                .collect(),
+            self.divisor.as_ref().map(|d| d.generate() as f32),
+            self.bias
+                .as_ref()
+                .map(|b| b.generate() as f32)
+                .unwrap_or(0.0),
+        ))
+    }
+}
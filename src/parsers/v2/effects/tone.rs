@@ -0,0 +1,186 @@
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::structure::value::{parse_property_as_f64, parse_property_as_usize, Vf64, Vusize},
+    utils::pipeline::tone::Tone,
+};
+
+#[derive(Debug)]
+/// Converts to greyscale, with `r`, `g` and `b` weights - Rec. 709 luma by default.
+pub struct Grayscale {
+    weights: [Vf64; 3],
+}
+
+impl Grayscale {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("grayscale").unwrap();
+        let weight =
+            |name: &str, default: f64| parse_property_as_f64(value, name).unwrap_or(default.into());
+
+        Self {
+            weights: [
+                weight("r", 0.2126),
+                weight("g", 0.7152),
+                weight("b", 0.0722),
+            ],
+        }
+    }
+
+    pub fn generate(&self) -> Tone {
+        Tone::Grayscale(self.weights.each_ref().map(|w| w.generate() as f32))
+    }
+}
+
+#[derive(Debug)]
+/// Cuts each channel down to a number of levels - `levels` sets every channel, which
+/// `r`, `g` and `b` then override.
+pub struct Posterize {
+    levels: [Vusize; 3],
+}
+
+impl Posterize {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("posterize").unwrap();
+        let all = parse_property_as_usize(value, "levels");
+        let channel = |name: &str| {
+            parse_property_as_usize(value, name)
+                .or(all.clone())
+                .expect("[posterize] needs `levels`, or a level for every channel.")
+        };
+
+        Self {
+            levels: [channel("r"), channel("g"), channel("b")],
+        }
+    }
+
+    pub fn generate(&self) -> Tone {
+        Tone::Posterize(self.levels.each_ref().map(|l| l.generate() as u32))
+    }
+}
+
+#[derive(Debug)]
+pub struct Threshold(Vf64);
+
+impl Threshold {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("threshold").unwrap();
+
+        let level = match value {
+            Value::Mapping(mapping) => {
+                if mapping.keys().any(|key| key.as_str() != Some("level")) {
+                    panic!("[threshold] only takes a `level`, like `threshold: {{ level: 0.5 }}`.");
+                }
+                parse_property_as_f64(value, "level")
+            }
+            Value::Null => None,
+            // the level on its own, like `threshold: 0.5`.
+            _ => Some(Vf64::property(value)),
+        };
+
+        Self(level.unwrap_or(0.5.into()))
+    }
+
+    pub fn generate(&self) -> Tone {
+        Tone::Threshold(self.0.generate() as f32)
+    }
+}
+
+#[derive(Debug)]
+pub struct Levels {
+    black: Vf64,
+    white: Vf64,
+    gamma: Vf64,
+}
+
+impl Levels {
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("levels").unwrap();
+
+        Self {
+            black: parse_property_as_f64(value, "black").unwrap_or(0.0.into()),
+            white: parse_property_as_f64(value, "white").unwrap_or(1.0.into()),
+            gamma: parse_property_as_f64(value, "gamma").unwrap_or(1.0.into()),
+        }
+    }
+
+    pub fn generate(&self) -> Tone {
+        Tone::Levels {
+            black: self.black.generate() as f32,
+            white: self.white.generate() as f32,
+            gamma: self.gamma.generate().max(f64::EPSILON) as f32,
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Maps channels through control points, each written as `[x, y]` - `all` sets every
+/// channel, which `r`, `g` and `b` then override.
+pub struct Curves {
+    channels: [Vec<(Vf64, Vf64)>; 3],
+}
+
+impl Curves {
+    fn parse_points(value: &Value, name: &str) -> Option<Vec<(Vf64, Vf64)>> {
+        let points = value.get(name)?.as_sequence().unwrap_or_else(|| {
+            panic!("[curves.{name}] must be a list of points.");
+        });
+
+        Some(
+            points
+                .iter()
+                .map(|point| match point.as_sequence().map(Vec::as_slice) {
+                    Some([x, y]) => (Vf64::property(x), Vf64::property(y)),
+                    _ => panic!("[curves.{name}] points must be written as [x, y]."),
+                })
+                .collect(),
+        )
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        let value = value.get("curves").unwrap();
+        let all = Self::parse_points(value, "all").unwrap_or_default();
+        let channel = |name: &str| Self::parse_points(value, name).unwrap_or(all.clone());
+
+        Self {
+            channels: [channel("r"), channel("g"), channel("b")],
+        }
+    }
+
+    pub fn generate(&self) -> Tone {
+        Tone::Curves(self.channels.each_ref().map(|points| {
+            let mut points: Vec<(f32, f32)> = points
+                .iter()
+                .map(|(x, y)| (x.generate() as f32, y.generate() as f32))
+                .collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            points
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threshold(yaml: &str) -> f32 {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        match Threshold::from_value(&value).generate() {
+            Tone::Threshold(level) => level,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn threshold_forms() {
+        assert_eq!(threshold("threshold: 0.25"), 0.25);
+        assert_eq!(threshold("threshold: { level: 0.25 }"), 0.25);
+        assert_eq!(threshold("threshold:"), 0.5);
+        assert_eq!(threshold("threshold: {}"), 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "only takes a `level`")]
+    fn threshold_rejects_other_keys() {
+        threshold("threshold: { min: 0.4, max: 0.6 }");
+    }
+}
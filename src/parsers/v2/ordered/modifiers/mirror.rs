@@ -2,7 +2,9 @@ use image_effects::dither::ordered::tools::mirror::{self, MirrorLine};
 use rand::seq::IndexedRandom;
 use serde_yaml::Value;
 
-use crate::parsers::v2::structure::value::{parse_property_as_f64, Chance, ValueProperty};
+use crate::parsers::v2::structure::value::{
+    iteration_rng, parse_property_as_f64, Chance, ValueProperty,
+};
 
 #[derive(Debug, Clone, Copy)]
 pub enum MirrorDirection {
@@ -90,7 +92,7 @@ impl Mirror {
             return vec![];
         }
 
-        let mirror = self.directions.choose(&mut rand::rng()).unwrap();
+        let mirror = self.directions.choose(&mut iteration_rng()).unwrap();
 
         mirror
             .iter()
@@ -2,7 +2,9 @@ use image_effects::dither::ordered::tools::properties;
 use rand::seq::IndexedRandom;
 use serde_yaml::Value;
 
-use crate::parsers::v2::structure::value::{parse_property_as_f64, Chance, ValueProperty};
+use crate::parsers::v2::structure::value::{
+    iteration_rng, parse_property_as_f64, Chance, ValueProperty,
+};
 
 #[derive(Debug)]
 pub enum RotationDirection {
@@ -64,7 +66,7 @@ impl Rotation {
         if !self.chance.roll() {
             None
         } else {
-            Some(self.values.choose(&mut rand::rng()).unwrap().to_tool())
+            Some(self.values.choose(&mut iteration_rng()).unwrap().to_tool())
         }
     }
 }
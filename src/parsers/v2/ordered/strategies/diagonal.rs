@@ -2,6 +2,8 @@ use image_effects::dither::ordered::algorithms::properties;
 use rand::Rng;
 use serde_yaml::Value;
 
+use crate::parsers::v2::structure::value::iteration_rng;
+
 #[derive(PartialEq, Eq, Hash, Debug)]
 pub enum DiagonalDirection {
     DownRight,
@@ -68,7 +70,7 @@ impl Diagonal {
             DiagonalKind::Ratios(ratios) => {
                 let capacity = ratios.iter().map(|(ratio, _)| ratio).sum();
 
-                let mut flag = rand::rng().random_range(0.0..capacity);
+                let mut flag = iteration_rng().random_range(0.0..capacity);
 
                 for (ratio, direction) in ratios {
                     flag -= ratio;
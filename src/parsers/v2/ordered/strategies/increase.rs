@@ -3,7 +3,7 @@ use rand::Rng;
 use serde_yaml::Value;
 
 use crate::parsers::v2::structure::value::{
-    parse_property_as_f64, parse_property_as_usize, Chance, ValueProperty, Vusize,
+    iteration_rng, parse_property_as_f64, parse_property_as_usize, Chance, ValueProperty, Vusize,
 };
 
 #[derive(PartialEq, Eq, Hash, Debug)]
@@ -97,7 +97,7 @@ impl Increase {
             IncreaseKind::Ratios(ratios) => {
                 let capacity = ratios.iter().map(|(ratio, _)| ratio).sum();
 
-                let mut flag = rand::rng().random_range(0.0..capacity);
+                let mut flag = iteration_rng().random_range(0.0..capacity);
 
                 for (ratio, increase) in ratios {
                     flag -= ratio;
@@ -2,6 +2,8 @@ use image_effects::dither::ordered::algorithms::properties;
 use rand::seq::IndexedRandom;
 use serde_yaml::Value;
 
+use crate::parsers::v2::structure::value::iteration_rng;
+
 #[derive(Debug)]
 pub enum WrappingKind {
     Horizontal,
@@ -62,6 +64,9 @@ impl Wrapping {
     }
 
     pub fn pick(&self) -> properties::Wrapping {
-        self.kinds.choose(&mut rand::rng()).unwrap().to_property()
+        self.kinds
+            .choose(&mut iteration_rng())
+            .unwrap()
+            .to_property()
     }
 }
@@ -1,6 +1,8 @@
 use rand::Rng;
 use serde_yaml::Value;
 
+use crate::parsers::v2::structure::value::iteration_rng;
+
 #[derive(Debug)]
 pub enum ChromaStrategyKind {
     Random,
@@ -24,7 +26,7 @@ impl ChromaStrategy {
     }
 
     pub fn attach_chroma(&self, colours: &Vec<(f32, f32)>) -> Vec<(f32, f32, f32)> {
-        let mut rng = rand::rng();
+        let mut rng = iteration_rng();
         match self.kind {
             ChromaStrategyKind::Random => colours
                 .iter()
@@ -2,7 +2,7 @@ use rand::Rng;
 use serde_yaml::Value;
 
 use crate::parsers::v2::structure::value::{
-    parse_property_as_f64, parse_property_as_usize, Vf64, Vusize,
+    iteration_rng, parse_property_as_f64, parse_property_as_usize, Vf64, Vusize,
 };
 
 #[derive(Debug)]
@@ -105,7 +105,8 @@ impl HueStrategyKind {
                     neighbourhood.push((lower_end + (size as f32 * 2.0 * fraction)) % 360.0);
                 }
                 HueDistribution::Random => {
-                    neighbourhood.push((rand::rng().random_range(lower_end..upper_end)) % 360.0);
+                    neighbourhood
+                        .push((iteration_rng().random_range(lower_end..upper_end)) % 360.0);
                 }
             }
         }
@@ -114,7 +115,7 @@ impl HueStrategyKind {
     }
 
     pub fn _execute(&self) -> Vec<f32> {
-        self.execute_with_seed_hue(rand::rng().random_range(0.0..360.0))
+        self.execute_with_seed_hue(iteration_rng().random_range(0.0..360.0))
     }
 
     pub fn execute_with_seed_hue(&self, seed_hue: f64) -> Vec<f32> {
@@ -192,7 +193,7 @@ impl HueStrategies {
     }
 
     pub fn generate_hues(&self) -> Vec<f32> {
-        let seed_hue = rand::rng().random_range(0.0..360.0);
+        let seed_hue = iteration_rng().random_range(0.0..360.0);
 
         self.kinds
             .iter()
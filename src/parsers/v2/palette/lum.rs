@@ -2,7 +2,7 @@ use rand::Rng;
 use serde_yaml::Value;
 
 use crate::parsers::v2::structure::value::{
-    parse_property_as_f64, parse_property_as_usize, ValueProperty, Vf64, Vusize,
+    iteration_rng, parse_property_as_f64, parse_property_as_usize, ValueProperty, Vf64, Vusize,
 };
 
 #[derive(Debug)]
@@ -47,7 +47,7 @@ impl LumStrategyKind {
                 .flat_map(|hue| exact.iter().map(|exact| (exact.generate() as f32, *hue)))
                 .collect(),
             Self::Random { stacks } => {
-                let mut rng = rand::rng();
+                let mut rng = iteration_rng();
                 let stacks = stacks.as_ref().map(|v| v.generate()).unwrap_or(1);
 
                 let mut colours = Vec::with_capacity(stacks * hues.len());
@@ -89,14 +89,14 @@ impl LumStrategyKind {
                         area_start = (area_start - overlap).max(min_lum);
                         area_end = (area_end + overlap).min(max_lum);
 
-                        let l = rand::rng().random_range(area_start..area_end) as f32;
+                        let l = iteration_rng().random_range(area_start..area_end) as f32;
                         cols.push((l, *hue));
                     }
                 }
                 cols
             }
             Self::StackDistributedNudge { nudge_size, stacks } => {
-                let mut rng = rand::rng();
+                let mut rng = iteration_rng();
                 let stacks = stacks.generate();
                 let mut cols = Vec::with_capacity(stacks * hues.len());
                 let nudge_size = nudge_size.generate();
@@ -127,7 +127,7 @@ impl LumStrategyKind {
                 let s_size = s_size.generate();
                 let c_min = c_min.generate();
                 let c_max = c_max.generate();
-                let mut rng = rand::rng();
+                let mut rng = iteration_rng();
 
                 let mut cols = Vec::with_capacity(hues.len() * (s_amnt + 1));
 
@@ -0,0 +1,133 @@
+use image::{imageops, DynamicImage, GrayImage, Luma, RgbaImage};
+use serde_yaml::Value;
+
+/// What happens to the alpha channel of a source after the effects are applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlphaMode {
+    /// Re-apply the source's alpha as-is.
+    Keep,
+    /// Make every pixel either opaque or transparent, cutting off at an alpha of 0-1.
+    Threshold(f64),
+    /// Like `Threshold`, but with an ordered dither - softer edges for 1-bit formats like GIF.
+    Dither,
+    /// Pass the alpha through the effects, even though they aren't made to handle it.
+    Ignore,
+}
+
+impl AlphaMode {
+    pub fn from_value(value: &Value) -> Self {
+        let mode = value
+            .get("mode")
+            .map(|v| v.as_str().expect("[alpha.mode] must be a string."))
+            .unwrap_or("keep");
+
+        match mode {
+            "keep" => Self::Keep,
+            "threshold" => Self::Threshold(
+                value
+                    .get("threshold")
+                    .map(|v| v.as_f64().expect("[alpha.threshold] must be a number."))
+                    .unwrap_or(0.5),
+            ),
+            "dither" => Self::Dither,
+            "ignore" => Self::Ignore,
+            _ => unimplemented!("alpha mode {mode} is not supported."),
+        }
+    }
+}
+
+/// Keeps transparency intact through effects that only understand colour.
+#[derive(Debug, Clone)]
+pub struct AlphaConfig {
+    pub mode: AlphaMode,
+    /// Treat every fully transparent pixel as the same colour when picking out a
+    /// palette, so hidden colours don't take up space in it. For GIFs, that colour is
+    /// their one transparent entry - without it, they're opaque.
+    pub skip_transparent: bool,
+}
+
+impl Default for AlphaConfig {
+    fn default() -> Self {
+        Self {
+            mode: AlphaMode::Keep,
+            skip_transparent: true,
+        }
+    }
+}
+
+impl AlphaConfig {
+    /// The 4x4 Bayer matrix, used to dither the alpha channel.
+    const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+    pub fn from_value(value: &Value) -> Self {
+        let mut default = Self::default();
+
+        let alpha = match value.get("alpha") {
+            Some(alpha) => alpha,
+            None => return default,
+        };
+
+        default.mode = AlphaMode::from_value(alpha);
+        if let Some(skip) = alpha.get("skip-transparent") {
+            default.skip_transparent = skip
+                .as_bool()
+                .expect("[alpha.skip-transparent] must be a boolean.");
+        }
+
+        default
+    }
+
+    /// Runs `effects` over the colour channels of `image` only, then puts its alpha back.
+    pub fn preserve<F>(&self, image: DynamicImage, effects: F) -> DynamicImage
+    where
+        F: FnOnce(DynamicImage) -> DynamicImage,
+    {
+        if self.mode == AlphaMode::Ignore || !image.color().has_alpha() {
+            return effects(image);
+        }
+
+        let rgba = image.to_rgba8();
+
+        // decoded video frames always come with an alpha channel, even if it's unused.
+        if rgba.pixels().all(|pixel| pixel.0[3] == 255) {
+            return effects(image);
+        }
+
+        let mut alpha = GrayImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+            Luma([rgba.get_pixel(x, y).0[3]])
+        });
+
+        let colour = DynamicImage::ImageRgb8(image.to_rgb8());
+        let mut result: RgbaImage = effects(colour).to_rgba8();
+
+        // some effects resize the image, so the alpha has to follow.
+        if alpha.dimensions() != result.dimensions() {
+            let (width, height) = result.dimensions();
+            alpha = imageops::resize(&alpha, width, height, imageops::Triangle);
+        }
+
+        for (x, y, pixel) in result.enumerate_pixels_mut() {
+            pixel.0[3] = self.alpha_at(alpha.get_pixel(x, y).0[0], x, y);
+        }
+
+        DynamicImage::ImageRgba8(result)
+    }
+
+    fn alpha_at(&self, alpha: u8, x: u32, y: u32) -> u8 {
+        let opaque = match self.mode {
+            AlphaMode::Keep | AlphaMode::Ignore => return alpha,
+            AlphaMode::Threshold(threshold) => alpha as f64 / 255.0 >= threshold,
+            AlphaMode::Dither => {
+                let bayer = Self::BAYER[(y % 4) as usize][(x % 4) as usize];
+                // maps the matrix to (0, 255) so fully transparent/opaque pixels stay that way.
+                alpha as u32 * 16 > bayer as u32 * 255 + 127
+            }
+        };
+
+        if opaque {
+            255
+        } else {
+            0
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use regex::Regex;
+use serde_yaml::Value;
+
+/// Rewrites part of a URL before it gets fetched.
+#[derive(Debug, Clone)]
+pub struct UrlRewrite {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+impl UrlRewrite {
+    pub fn new(pattern: &str, replacement: impl ToString) -> Self {
+        Self {
+            pattern: Regex::new(pattern)
+                .unwrap_or_else(|e| panic!("invalid rewrite pattern [{pattern}]: {e}")),
+            replacement: replacement.to_string(),
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        let pattern = value
+            .get("pattern")
+            .and_then(|v| v.as_str())
+            .expect("[fetch.rewrite] entries need a `pattern` string.");
+        let replacement = value
+            .get("replace")
+            .map(|v| {
+                v.as_str()
+                    .expect("[fetch.rewrite.replace] must be a string.")
+            })
+            .unwrap_or("");
+
+        Self::new(pattern, replacement)
+    }
+
+    pub fn apply(&self, url: &str) -> String {
+        self.pattern
+            .replace_all(url, self.replacement.as_str())
+            .to_string()
+    }
+}
+
+/// Where downloads are cached with `cache: true`.
+const DEFAULT_CACHE: &str = "temp/cache";
+
+/// How URL sources are downloaded.
+#[derive(Debug, Clone)]
+pub struct FetchConfig {
+    /// How long a single request may take.
+    pub timeout: Duration,
+    /// How many times a failed request is retried.
+    pub retries: usize,
+    /// How long to wait before the first retry - it doubles after each one (up to a
+    /// minute), unless the server says how long to wait.
+    pub backoff: Duration,
+    /// Where downloads are cached - `None` (the default) always downloads. Nothing
+    /// expires, so this is opt-in.
+    pub cache: Option<String>,
+    /// Applied to the URL in order, before anything is fetched.
+    pub rewrites: Vec<UrlRewrite>,
+}
+
+impl Default for FetchConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            retries: 2,
+            backoff: Duration::from_millis(500),
+            cache: None,
+            // discord's media proxy serves a downscaled image if these are kept.
+            rewrites: vec![
+                UrlRewrite::new(r"&width=[0-9]+", ""),
+                UrlRewrite::new(r"&height=[0-9]+", ""),
+            ],
+        }
+    }
+}
+
+impl FetchConfig {
+    pub fn from_value(value: &Value) -> Self {
+        let mut default = Self::default();
+
+        let fetch = match value.get("fetch") {
+            Some(fetch) => fetch,
+            None => return default,
+        };
+
+        if let Some(timeout) = fetch.get("timeout") {
+            let seconds = timeout
+                .as_f64()
+                .expect("[fetch.timeout] must be a number of seconds.");
+            default.timeout = Duration::from_secs_f64(seconds);
+        }
+
+        if let Some(retries) = fetch.get("retries") {
+            default.retries = retries
+                .as_u64()
+                .expect("[fetch.retries] must be a positive integer.")
+                as usize;
+        }
+
+        if let Some(backoff) = fetch.get("backoff") {
+            let seconds = backoff
+                .as_f64()
+                .expect("[fetch.backoff] must be a number of seconds.");
+            default.backoff = Duration::from_secs_f64(seconds);
+        }
+
+        if let Some(cache) = fetch.get("cache") {
+            default.cache = match cache {
+                Value::Bool(false) => None,
+                Value::Bool(true) => Some(DEFAULT_CACHE.into()),
+                Value::String(path) => Some(path.clone()),
+                _ => unimplemented!("[fetch.cache] must be a boolean or a directory."),
+            };
+        }
+
+        if let Some(rewrites) = fetch.get("rewrite") {
+            default.rewrites = rewrites
+                .as_sequence()
+                .expect("[fetch.rewrite] must be a list.")
+                .iter()
+                .map(UrlRewrite::from_value)
+                .collect();
+        }
+
+        default
+    }
+
+    pub fn rewrite(&self, url: &str) -> String {
+        self.rewrites
+            .iter()
+            .fold(url.to_string(), |url, rewrite| rewrite.apply(&url))
+    }
+}
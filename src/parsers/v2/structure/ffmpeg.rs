@@ -0,0 +1,119 @@
+use serde_yaml::Value;
+
+/// How the palette of GIF outputs is generated.
+#[derive(Debug, Clone, Copy)]
+pub enum GifPaletteMode {
+    /// Let ffmpeg use its default (fixed) palette.
+    None,
+    /// Generate a single palette from every frame.
+    ///
+    /// Note that ffmpeg has to buffer the whole output to do this.
+    Global,
+    /// Generate a new palette for each frame.
+    PerFrame,
+    /// Generate a palette favouring the parts of frames that change.
+    Diff,
+}
+
+impl GifPaletteMode {
+    pub fn from_value(value: &Value) -> Self {
+        let mode = value.as_str().unwrap();
+
+        match mode {
+            "none" => Self::None,
+            "global" => Self::Global,
+            "per-frame" => Self::PerFrame,
+            "diff" => Self::Diff,
+            _ => unimplemented!("gif palette mode {mode} is not supported."),
+        }
+    }
+
+    /// The filtergraph that applies this palette mode, if any.
+    ///
+    /// With `skip_transparent`, one entry is kept for transparency and transparent pixels
+    /// aren't counted towards the rest - otherwise the GIF comes out opaque.
+    pub fn filter(&self, skip_transparent: bool) -> Option<String> {
+        let (stats_mode, options) = match self {
+            Self::None => return None,
+            Self::Global => ("full", ""),
+            Self::PerFrame => ("single", "=new=1"),
+            Self::Diff => ("diff", "=diff_mode=rectangle"),
+        };
+
+        Some(format!(
+            "split[a][b];[a]palettegen=stats_mode={stats_mode}:reserve_transparent={}[p];[b][p]paletteuse{options}",
+            skip_transparent as u8
+        ))
+    }
+}
+
+/// Settings passed through to ffmpeg when decoding and encoding animated media.
+#[derive(Debug, Clone)]
+pub struct FfmpegConfig {
+    /// Hardware acceleration used while decoding - `None` decodes on the CPU.
+    pub hwaccel: Option<String>,
+    /// Video codec of the output, e.g. `libx264`. Picked by ffmpeg if missing, and ignored for GIFs.
+    pub codec: Option<String>,
+    /// Pixel format of the output, e.g. `yuv420p`. Ignored for GIFs.
+    pub pixel_format: Option<String>,
+    /// Ignored for GIFs.
+    pub crf: Option<u64>,
+    /// Target bitrate of the output, e.g. `2M`. Ignored for GIFs.
+    pub bitrate: Option<String>,
+    pub gif_palette: GifPaletteMode,
+    /// How many times the output loops - `0` loops forever. Only used by GIFs.
+    pub loop_count: Option<i64>,
+}
+
+impl Default for FfmpegConfig {
+    fn default() -> Self {
+        Self {
+            hwaccel: None,
+            codec: None,
+            pixel_format: None,
+            crf: None,
+            bitrate: None,
+            gif_palette: GifPaletteMode::None,
+            loop_count: None,
+        }
+    }
+}
+
+impl FfmpegConfig {
+    pub fn from_value(value: &Value) -> Self {
+        let mut default = Self::default();
+
+        let ffmpeg = match value.get("ffmpeg") {
+            Some(ffmpeg) => ffmpeg,
+            None => return default,
+        };
+
+        let as_string = |key: &str| {
+            ffmpeg
+                .get(key)
+                .map(|v| {
+                    v.as_str()
+                        .unwrap_or_else(|| panic!("[ffmpeg.{key}] must be a string."))
+                })
+                .map(|v| v.to_string())
+        };
+
+        default.hwaccel = as_string("hwaccel").filter(|hwaccel| hwaccel != "none");
+        default.codec = as_string("codec");
+        default.pixel_format = as_string("pixel-format");
+        default.bitrate = as_string("bitrate");
+        default.crf = ffmpeg.get("crf").map(|v| {
+            v.as_u64()
+                .expect("[ffmpeg.crf] must be a positive integer.")
+        });
+        default.loop_count = ffmpeg
+            .get("loop")
+            .map(|v| v.as_i64().expect("[ffmpeg.loop] must be an integer."));
+
+        if let Some(mode) = ffmpeg.get("gif-palette") {
+            default.gif_palette = GifPaletteMode::from_value(mode);
+        }
+
+        default
+    }
+}
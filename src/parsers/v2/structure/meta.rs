@@ -1,36 +1,47 @@
+use std::{fs::OpenOptions, io::ErrorKind, path::Path};
 
 use image::{imageops, DynamicImage, GenericImageView};
-use regex::Regex;
 use serde_yaml::Value;
 
-use crate::utils::resource::image::parser::{ImageKind, ImageParser, ImageResult};
+use crate::{
+    parsers::v2::structure::{
+        fetch::FetchConfig,
+        output::{IndexedMode, NameTemplate, OutputFormat, PngCompression, TextOutput},
+    },
+    utils::resource::image::parser::{ImageKind, ImageParser, ImageResult},
+};
 
 #[derive(Debug)]
 pub enum SourceKind {
-    Url(String),
+    Url(String, FetchConfig),
     File(String),
+    /// Read from stdin, for use in pipelines.
+    Stdin,
 }
 
 impl SourceKind {
     pub fn from_value(value: &Value) -> Self {
         let file = value.get("file");
         let url = value.get("url");
+        let stdin = value
+            .get("stdin")
+            .filter(|v| v.as_bool().expect("[stdin] must be a boolean."));
 
-        if file.and(url).is_some() {
-            unimplemented!("only one of file/url accepted")
-        } else if file.or(url).is_none() {
-            unimplemented!("at least one of file/url required")
+        let given = [file, url, stdin].iter().filter(|v| v.is_some()).count();
+
+        if given > 1 {
+            unimplemented!("only one of file/url/stdin accepted")
+        } else if given == 0 {
+            unimplemented!("at least one of file/url/stdin required")
         }
 
-        if let Some(file) = file {
+        if stdin.is_some() {
+            SourceKind::Stdin
+        } else if let Some(file) = file {
             SourceKind::File(file.as_str().unwrap().to_string())
         } else if let Some(url) = url {
-            let url = url.as_str().unwrap();
-            let wre = Regex::new(r"&width=[0-9]+").unwrap();
-            let hre = Regex::new(r"&height=[0-9]+").unwrap();
-            let url = wre.replace_all(&url, "");
-            let url = hre.replace_all(&url, "");
-            SourceKind::Url(url.to_string())
+            let fetch = FetchConfig::from_value(value);
+            SourceKind::Url(fetch.rewrite(url.as_str().unwrap()), fetch)
         } else {
             unimplemented!("we shouldn't have even reached this point...")
         }
@@ -39,69 +50,433 @@ impl SourceKind {
     pub fn get_path(&self) -> String {
         match self {
             Self::File(path) => path.to_string(),
-            Self::Url(path) => path.to_string(),
+            Self::Url(path, _) => path.to_string(),
+            Self::Stdin => "stdin".to_string(),
         }
     }
 
+    /// The name of the source without its extension, for naming outputs.
+    pub fn stem(&self) -> String {
+        let path = match self {
+            Self::File(path) => path.as_str(),
+            // query strings and fragments aren't part of the name.
+            Self::Url(url, _) => url.split(['?', '#']).next().unwrap(),
+            Self::Stdin => return "stdin".to_string(),
+        };
+
+        Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or("source".to_string())
+    }
+
     pub fn get_image_kind(&self) -> ImageKind {
         match self {
             SourceKind::File(path) => ImageParser::parse_localkind(path),
-            SourceKind::Url(path) => ImageParser::parse_webkind(path),
+            SourceKind::Url(path, fetch) => ImageParser::parse_webkind(path, fetch),
+            SourceKind::Stdin => ImageParser::parse_stdinkind(),
         }
     }
 }
 
-#[derive(Debug)]
-pub enum SizeConstraint {
+/// The filter used whenever an image is resized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resample {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl Resample {
+    pub fn from_value(value: &Value) -> Self {
+        let filter = value.as_str().expect("resample filters must be strings.");
+
+        match filter {
+            "nearest" => Self::Nearest,
+            "triangle" => Self::Triangle,
+            "catmull-rom" => Self::CatmullRom,
+            "lanczos3" => Self::Lanczos3,
+            _ => unimplemented!("resample filter {filter} is not supported."),
+        }
+    }
+
+    pub fn to_filter_type(self) -> imageops::FilterType {
+        match self {
+            Self::Nearest => imageops::Nearest,
+            Self::Triangle => imageops::Triangle,
+            Self::CatmullRom => imageops::CatmullRom,
+            Self::Lanczos3 => imageops::Lanczos3,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nearest => "nearest",
+            Self::Triangle => "triangle",
+            Self::CatmullRom => "catmull-rom",
+            Self::Lanczos3 => "lanczos3",
+        }
+    }
+}
+
+/// How an image is made to match an exact size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExactMode {
+    /// Scale to fit inside the size, keeping the aspect ratio - so one side can come
+    /// out short.
+    Fit,
+    /// Stretch to the size.
+    Fill,
+    /// Scale to cover the size, keeping the aspect ratio, then crop whatever's left over.
+    /// The default, as it's the only mode that's both exact and undistorted.
+    Crop,
+}
+
+impl ExactMode {
+    pub fn from_value(value: &Value) -> Self {
+        let mode = value.as_str().expect("[fit] must be a string.");
+
+        match mode {
+            "fit" => Self::Fit,
+            "fill" => Self::Fill,
+            "crop" => Self::Crop,
+            _ => unimplemented!("fit mode {mode} is not supported."),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstraintKind {
     MaxDim(usize),
     MaxPixels(usize),
+    /// Scales up until the shorter side is at least this long.
+    MinDim(usize),
+    Scale(f64),
+    Exact(u32, u32, ExactMode),
 }
 
-impl SizeConstraint {
+impl ConstraintKind {
     pub fn from_value(value: &Value) -> Option<Self> {
         if let Some(value) = value.get("max-dim") {
             Some(Self::MaxDim(value.as_u64().unwrap() as usize))
         } else if let Some(value) = value.get("max-pixels") {
             Some(Self::MaxPixels(value.as_u64().unwrap() as usize))
+        } else if let Some(value) = value.get("min-dim") {
+            Some(Self::MinDim(value.as_u64().unwrap() as usize))
+        } else if let Some(value) = value.get("scale") {
+            Some(Self::Scale(
+                value.as_f64().expect("[scale] must be a number."),
+            ))
+        } else if let Some(exact) = value.get("exact") {
+            let (width, height) = Self::parse_size(exact);
+            let mode = value
+                .get("fit")
+                .map(ExactMode::from_value)
+                .unwrap_or(ExactMode::Crop);
+            Some(Self::Exact(width, height, mode))
         } else {
             None
         }
     }
 
+    /// Parses a size as either `WxH`, or a mapping with a `width` and `height`.
+    pub fn parse_size(value: &Value) -> (u32, u32) {
+        if let Some(size) = value.as_str() {
+            let parse = |n: &str| {
+                n.trim()
+                    .parse::<u32>()
+                    .unwrap_or_else(|_| panic!("invalid size: {size}"))
+            };
+
+            match size.split_once('x') {
+                Some((width, height)) => (parse(width), parse(height)),
+                None => panic!("sizes must look like WxH, not {size}"),
+            }
+        } else {
+            let side = |key: &str| {
+                value
+                    .get(key)
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or_else(|| panic!("sizes need a `{key}`.")) as u32
+            };
+
+            (side("width"), side("height"))
+        }
+    }
+
     pub fn as_string(&self) -> String {
         match self {
             Self::MaxDim(n) => format!("max-dim: {n}"),
             Self::MaxPixels(n) => format!("max-pixels: {n}"),
+            Self::MinDim(n) => format!("min-dim: {n}"),
+            Self::Scale(n) => format!("scale: {n}"),
+            Self::Exact(x, y, mode) => format!("exact: {x}x{y} ({mode:?})"),
         }
     }
 
-    pub fn constrain(&self, image: DynamicImage) -> DynamicImage {
-        let (x, y) = image.dimensions();
-
+    /// The factor the dimensions need to be scaled by, if they break the constraint.
+    fn factor(&self, (x, y): (u32, u32)) -> Option<f32> {
         match self {
             Self::MaxDim(max_dim) => {
                 if *max_dim < x.max(y) as usize {
-                    Self::resize_image(image, *max_dim as f32 / x.max(y) as f32)
+                    Some(*max_dim as f32 / x.max(y) as f32)
                 } else {
-                    image
+                    None
                 }
             }
             Self::MaxPixels(max_pixels) => {
                 let pixels = x * y;
 
                 if pixels > *max_pixels as u32 {
-                    Self::resize_image(image, (*max_pixels as f32 / pixels as f32).sqrt())
+                    Some((*max_pixels as f32 / pixels as f32).sqrt())
                 } else {
-                    image
+                    None
+                }
+            }
+            Self::MinDim(min_dim) => {
+                if *min_dim > x.min(y) as usize {
+                    Some(*min_dim as f32 / x.min(y) as f32)
+                } else {
+                    None
                 }
             }
+            Self::Scale(factor) => Some(*factor as f32),
+            Self::Exact(width, height, mode) => {
+                let fx = *width as f32 / x as f32;
+                let fy = *height as f32 / y as f32;
+
+                match mode {
+                    ExactMode::Fit => Some(fx.min(fy)),
+                    ExactMode::Crop => Some(fx.max(fy)),
+                    ExactMode::Fill => unreachable!("filling doesn't keep the aspect ratio"),
+                }
+            }
+        }
+    }
+
+    /// The size an image of size `(x, y)` gets resized to.
+    fn resized_dimensions(&self, (x, y): (u32, u32)) -> (u32, u32) {
+        let mul = |int: u32, float: f32| ((int as f32 * float).round() as u32).max(1);
+
+        match self {
+            Self::Exact(width, height, ExactMode::Fill) => (*width, *height),
+            _ => match self.factor((x, y)) {
+                Some(factor) => (mul(x, factor), mul(y, factor)),
+                None => (x, y),
+            },
+        }
+    }
+
+    /// The size an image of size `(x, y)` ends up as - crops included.
+    fn final_dimensions(&self, dims: (u32, u32)) -> (u32, u32) {
+        match self {
+            Self::Exact(width, height, ExactMode::Crop) => (*width, *height),
+            _ => self.resized_dimensions(dims),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SizeConstraint {
+    pub kind: Option<ConstraintKind>,
+    /// Crops the dimensions down to a multiple of this - some codecs need even sizes.
+    pub multiple_of: Option<u32>,
+    pub filter: Resample,
+}
+
+impl SizeConstraint {
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let kind = ConstraintKind::from_value(value);
+        let multiple_of = value.get("multiple-of").map(|v| {
+            v.as_u64()
+                .filter(|n| *n > 0)
+                .expect("[multiple-of] must be a positive integer.") as u32
+        });
+
+        if kind.is_none() && multiple_of.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            kind,
+            multiple_of,
+            filter: value
+                .get("filter")
+                .map(Resample::from_value)
+                .unwrap_or(Resample::Nearest),
+        })
+    }
+
+    pub fn as_string(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(kind) = &self.kind {
+            parts.push(kind.as_string());
+        }
+        if let Some(n) = self.multiple_of {
+            parts.push(format!("multiple-of: {n}"));
+        }
+        parts.push(format!("filter: {}", self.filter.as_str()));
+
+        parts.join(", ")
+    }
+
+    fn round_to_multiple(&self, (x, y): (u32, u32)) -> (u32, u32) {
+        match self.multiple_of {
+            Some(n) => {
+                // anything smaller than `n` can't be cropped down to it.
+                let round = |d: u32| if d < n { d } else { d / n * n };
+                (round(x), round(y))
+            }
+            None => (x, y),
         }
     }
 
-    pub fn resize_image(image: DynamicImage, factor: f32) -> DynamicImage {
+    /// Calculates the dimensions an image of size `(x, y)` would end up with,
+    /// without needing the image itself. Used when streaming frames.
+    pub fn constrain_dimensions(&self, dims: (u32, u32)) -> (u32, u32) {
+        let dims = match &self.kind {
+            Some(kind) => kind.final_dimensions(dims),
+            None => dims,
+        };
+
+        self.round_to_multiple(dims)
+    }
+
+    pub fn constrain(&self, image: DynamicImage) -> DynamicImage {
+        let filter = self.filter.to_filter_type();
+        let dims = image.dimensions();
+
+        let image = match &self.kind {
+            Some(ConstraintKind::Exact(x, y, ExactMode::Crop)) => {
+                image.resize_to_fill(*x, *y, filter)
+            }
+            Some(kind) => {
+                let (x, y) = kind.resized_dimensions(dims);
+                if (x, y) == dims {
+                    image
+                } else {
+                    image.resize_exact(x, y, filter)
+                }
+            }
+            None => image,
+        };
+
+        // rounding crops rather than resizes, so the aspect ratio isn't distorted.
         let (x, y) = image.dimensions();
-        let mul = |int: u32, float: f32| (int as f32 * float) as u32;
-        image.resize(mul(x, factor), mul(y, factor), imageops::Nearest)
+        let (rx, ry) = self.round_to_multiple((x, y));
+        if (rx, ry) == (x, y) {
+            image
+        } else {
+            image.crop_imm((x - rx) / 2, (y - ry) / 2, rx, ry)
+        }
+    }
+}
+
+/// Limits which part of an animated source gets processed.
+///
+/// Useful for previewing a look without processing the whole thing.
+#[derive(Debug, Clone, Default)]
+pub struct Trim {
+    /// Where to start, in seconds.
+    pub start: Option<f64>,
+    /// Where to stop, in seconds.
+    pub end: Option<f64>,
+    /// How much to process from `start`, in seconds. Can't be used alongside `end`.
+    pub duration: Option<f64>,
+    /// Only keep every nth frame - the frame rate is lowered to match.
+    pub every_nth_frame: Option<usize>,
+    /// Stop after this many frames have been processed.
+    pub max_frames: Option<usize>,
+}
+
+impl Trim {
+    /// Parses a time as either seconds, or a timestamp such as `01:30` or `00:01:30.5`.
+    fn parse_time(value: &Value) -> f64 {
+        if let Some(seconds) = value.as_f64() {
+            seconds
+        } else if let Some(timestamp) = value.as_str() {
+            timestamp.split(':').fold(0.0, |acc, part| {
+                acc * 60.0
+                    + part
+                        .parse::<f64>()
+                        .unwrap_or_else(|_| panic!("invalid timestamp: {timestamp}"))
+            })
+        } else {
+            unimplemented!("times must be in seconds or a timestamp.")
+        }
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        let trim = Self {
+            start: value.get("start").map(Self::parse_time),
+            end: value.get("end").map(Self::parse_time),
+            duration: value.get("duration").map(Self::parse_time),
+            every_nth_frame: value
+                .get("every-nth-frame")
+                .map(|v| v.as_u64().unwrap().max(1) as usize),
+            max_frames: value
+                .get("max-frames")
+                .map(|v| v.as_u64().unwrap() as usize),
+        };
+
+        if trim.end.and(trim.duration).is_some() {
+            unimplemented!("only one of end/duration accepted")
+        }
+
+        for (key, time) in [("start", trim.start), ("end", trim.end)] {
+            if time.is_some_and(|time| time < 0.0) {
+                panic!("[source.{key}] can't be negative.");
+            }
+        }
+        if trim.duration.is_some_and(|duration| duration <= 0.0) {
+            panic!("[source.duration] must be more than 0.");
+        }
+        if trim.end.is_some_and(|end| end <= trim.start.unwrap_or(0.0)) {
+            panic!("[source.end] must come after [source.start].");
+        }
+        if trim.max_frames == Some(0) {
+            panic!("[source.max-frames] must be at least 1.");
+        }
+
+        trim
+    }
+
+    /// How much of the source is processed from `start`, in seconds.
+    pub fn recording_time(&self) -> Option<f64> {
+        self.duration
+            .or(self.end.map(|end| end - self.start.unwrap_or(0.0)))
+    }
+
+    pub fn step(&self) -> usize {
+        self.every_nth_frame.unwrap_or(1)
+    }
+
+    pub fn is_trimmed(&self) -> bool {
+        self.start.is_some()
+            || self.recording_time().is_some()
+            || self.step() > 1
+            || self.max_frames.is_some()
+    }
+
+    pub fn as_string(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(start) = self.start {
+            parts.push(format!("start: {start}s"));
+        }
+        if let Some(time) = self.recording_time() {
+            parts.push(format!("duration: {time}s"));
+        }
+        if self.step() > 1 {
+            parts.push(format!("every-nth-frame: {}", self.step()));
+        }
+        if let Some(max_frames) = self.max_frames {
+            parts.push(format!("max-frames: {max_frames}"));
+        }
+
+        parts.join(", ")
     }
 }
 
@@ -110,6 +485,7 @@ pub struct Source {
     pub kind: SourceKind,
     // media_type: MediaType,
     pub constraint: Option<SizeConstraint>,
+    pub trim: Trim,
 }
 
 impl Source {
@@ -120,6 +496,7 @@ impl Source {
             kind: SourceKind::from_value(source),
             // media_type: MediaType::from_value(source),
             constraint: SizeConstraint::from_value(source),
+            trim: Trim::from_value(source),
         }
     }
 
@@ -131,36 +508,29 @@ impl Source {
     }
 
     pub fn perform(&self) -> ImageResult {
-        let result = match &self.kind {
-            SourceKind::File(target) => {
-                let file = ImageParser::parse_localfile(target);
-
-                if self.constraint.is_some() {
-                    if let ImageResult::Image(image) = file {
-                        self.constraint.as_ref().unwrap().constrain(image).into()
-                    } else {
-                        file
-                    }
-                } else {
-                    file
-                }
-            }
-            SourceKind::Url(target) => {
-                let file = ImageParser::parse_webfile(&target);
-
-                if self.constraint.is_some() {
-                    if let ImageResult::Image(image) = file {
-                        self.constraint.as_ref().unwrap().constrain(image).into()
-                    } else {
-                        file
-                    }
-                } else {
-                    file
-                }
-            }
-        };
+        let file = ImageParser::parse_file(&self.kind);
+
+        match (&self.constraint, file) {
+            (Some(constraint), ImageResult::Image(image)) => constraint.constrain(image).into(),
+            (_, file) => file,
+        }
+    }
+}
+
+/// Whether the audio of animated sources makes it into the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioMode {
+    Keep,
+    Drop,
+}
 
-        result
+impl AudioMode {
+    pub fn from_value(value: &Value) -> Self {
+        match value.get("audio").map(|v| v.as_str().unwrap()) {
+            None | Some("keep") => Self::Keep,
+            Some("drop") => Self::Drop,
+            Some(mode) => unimplemented!("audio mode {mode} is not supported."),
+        }
     }
 }
 
@@ -168,6 +538,19 @@ impl Source {
 pub struct Output {
     pub path: String,
     pub n: usize,
+    pub audio: AudioMode,
+    pub format: OutputFormat,
+    /// Quality of lossy formats (JPEG and AVIF), from 1 to 100.
+    pub quality: Option<u8>,
+    pub compression: PngCompression,
+    pub indexed: IndexedMode,
+    pub text: TextOutput,
+    pub name: NameTemplate,
+    /// Whether existing files can be replaced - otherwise a number is added to the name.
+    pub overwrite: bool,
+    /// Seeds the first iteration, with each following one using the next seed.
+    /// Random if missing.
+    pub seed: Option<u64>,
 }
 
 impl Output {
@@ -177,6 +560,164 @@ impl Output {
         Self {
             path: output.get("path").unwrap().as_str().unwrap().to_string(),
             n: output.get("n").unwrap().as_u64().unwrap() as usize,
+            audio: AudioMode::from_value(output),
+            format: output
+                .get("format")
+                .map(OutputFormat::from_value)
+                .unwrap_or(OutputFormat::Png),
+            quality: output.get("quality").map(|v| {
+                let quality = v
+                    .as_u64()
+                    .expect("[output.quality] must be a positive integer.");
+                quality.clamp(1, 100) as u8
+            }),
+            compression: output
+                .get("compression")
+                .map(PngCompression::from_value)
+                .unwrap_or(PngCompression::Fast),
+            indexed: output
+                .get("indexed")
+                .map(IndexedMode::from_value)
+                .unwrap_or(IndexedMode::Auto),
+            text: TextOutput::from_value(output),
+            name: output
+                .get("name")
+                .map(NameTemplate::from_value)
+                .unwrap_or_default(),
+            overwrite: output
+                .get("overwrite")
+                .map(|v| v.as_bool().expect("[output.overwrite] must be a boolean."))
+                .unwrap_or(false),
+            seed: output.get("seed").map(|v| {
+                v.as_u64()
+                    .expect("[output.seed] must be a positive integer.")
+            }),
         }
     }
+
+    /// Picks the path for an output named `name`, in `dir`.
+    ///
+    /// Unless overwriting is allowed, the file is created straight away so that parallel
+    /// iterations can't claim the same path - and if it's already taken, a number is added.
+    pub fn reserve_path(&self, dir: &str, name: &str, extension: &str) -> String {
+        let path = format!("{dir}/{name}.{extension}");
+        if self.overwrite {
+            return path;
+        }
+
+        let mut candidate = path.clone();
+        for n in 1.. {
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&candidate)
+            {
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    candidate = format!("{dir}/{name}-{n}.{extension}");
+                }
+                Err(e) => panic!("couldn't create output [{candidate}]: {e}"),
+            }
+        }
+
+        if candidate != path {
+            eprintln!("[ ! ] | [{path}] already exists, saving to [{candidate}] instead.");
+        }
+
+        candidate
+    }
+
+    /// Whether the result is written to stdout rather than a directory.
+    pub fn is_stdout(&self) -> bool {
+        self.path == "-"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trim(yaml: &str) -> Trim {
+        Trim::from_value(&serde_yaml::from_str(yaml).unwrap())
+    }
+
+    #[test]
+    fn exact_sizes_are_exact_by_default() {
+        let value = serde_yaml::from_str("exact: 640x480").unwrap();
+        let constraint = ConstraintKind::from_value(&value).unwrap();
+
+        assert_eq!(constraint.final_dimensions((1920, 1080)), (640, 480));
+        assert_eq!(constraint.final_dimensions((300, 900)), (640, 480));
+    }
+
+    #[test]
+    fn times_are_seconds_or_timestamps() {
+        assert_eq!(trim("start: 2.5").start, Some(2.5));
+        assert_eq!(trim("start: '01:30'").start, Some(90.0));
+        assert_eq!(trim("start: '00:01:30.5'").start, Some(90.5));
+    }
+
+    #[test]
+    fn end_is_measured_from_start() {
+        assert_eq!(trim("{ start: 2, end: 5 }").recording_time(), Some(3.0));
+        assert_eq!(trim("end: 5").recording_time(), Some(5.0));
+        assert_eq!(
+            trim("{ start: 2, duration: 5 }").recording_time(),
+            Some(5.0)
+        );
+        assert_eq!(trim("start: 2").recording_time(), None);
+    }
+
+    #[test]
+    fn frame_options() {
+        let frames = trim("{ every-nth-frame: 3, max-frames: 10 }");
+        assert_eq!(frames.step(), 3);
+        assert_eq!(frames.max_frames, Some(10));
+        assert_eq!(frames.as_string(), "every-nth-frame: 3, max-frames: 10");
+
+        assert_eq!(trim("every-nth-frame: 0").step(), 1);
+    }
+
+    #[test]
+    fn untrimmed_by_default() {
+        let untrimmed = trim("path: source.gif");
+        assert!(!untrimmed.is_trimmed());
+        assert_eq!(untrimmed.as_string(), "");
+    }
+
+    #[test]
+    #[should_panic(expected = "only one of end/duration")]
+    fn end_and_duration_clash() {
+        trim("{ end: 5, duration: 5 }");
+    }
+
+    #[test]
+    #[should_panic(expected = "[source.end] must come after [source.start]")]
+    fn end_before_start_is_rejected() {
+        trim("{ start: 5, end: '00:03' }");
+    }
+
+    #[test]
+    #[should_panic(expected = "[source.start] can't be negative")]
+    fn negative_times_are_rejected() {
+        trim("start: -1");
+    }
+
+    #[test]
+    #[should_panic(expected = "[source.duration] must be more than 0")]
+    fn empty_durations_are_rejected() {
+        trim("duration: 0");
+    }
+
+    #[test]
+    #[should_panic(expected = "[source.max-frames] must be at least 1")]
+    fn zero_max_frames_are_rejected() {
+        trim("max-frames: 0");
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid timestamp")]
+    fn bad_timestamps_are_rejected() {
+        trim("start: '1:xx'");
+    }
 }
@@ -2,10 +2,18 @@ use serde_yaml::Value;
 
 use crate::parsers::v2::{
     effects::Effects,
-    structure::meta::{Output, Source},
+    structure::{
+        alpha::AlphaConfig,
+        ffmpeg::FfmpegConfig,
+        meta::{Output, Source},
+    },
 };
 
+pub mod alpha;
+pub mod fetch;
+pub mod ffmpeg;
 pub mod meta;
+pub mod output;
 pub mod value;
 
 #[derive(Debug)]
@@ -13,6 +21,8 @@ pub struct MainConfiguration {
     pub source: Source,
     pub output: Output,
     pub effects: Effects,
+    pub ffmpeg: FfmpegConfig,
+    pub alpha: AlphaConfig,
 }
 
 impl MainConfiguration {
@@ -21,6 +31,8 @@ impl MainConfiguration {
             source: Source::from_value(value),
             output: Output::from_value(value),
             effects: Effects::from_value(value),
+            ffmpeg: FfmpegConfig::from_value(value),
+            alpha: AlphaConfig::from_value(value),
         }
     }
 }
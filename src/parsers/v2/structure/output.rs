@@ -0,0 +1,280 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::codecs::png::CompressionType;
+use regex::Regex;
+use serde_yaml::Value;
+
+use crate::{
+    parsers::v2::{effects::ascii::parse_ramp, structure::meta::SizeConstraint},
+    utils::pipeline::ascii,
+};
+
+/// The format still images are saved in. Animated outputs are encoded by ffmpeg instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    /// Always lossless - `image` has no lossy WebP encoder.
+    Webp,
+    Qoi,
+    Tiff,
+    Bmp,
+    /// Encoded through ffmpeg, since `image` is built without its AVIF encoder.
+    Avif,
+    /// Plain text, with a character for each cell of the grid.
+    Txt,
+    /// Like `Txt`, but each character is coloured with ANSI escape codes.
+    Ansi,
+}
+
+impl OutputFormat {
+    pub fn from_value(value: &Value) -> Self {
+        let format = value.as_str().expect("[output.format] must be a string.");
+
+        match format {
+            "png" => Self::Png,
+            "jpeg" | "jpg" => Self::Jpeg,
+            "webp" => Self::Webp,
+            "qoi" => Self::Qoi,
+            "tiff" => Self::Tiff,
+            "bmp" => Self::Bmp,
+            "avif" => Self::Avif,
+            "txt" => Self::Txt,
+            "ansi" => Self::Ansi,
+            _ => unimplemented!("output format {format} is not supported."),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+            Self::Qoi => "qoi",
+            Self::Tiff => "tiff",
+            Self::Bmp => "bmp",
+            Self::Avif => "avif",
+            Self::Txt => "txt",
+            Self::Ansi => "ans",
+        }
+    }
+}
+
+/// How `txt` and `ansi` outputs turn the image into characters.
+#[derive(Debug, Clone)]
+pub struct TextOutput {
+    /// Decides how many characters there are - each is about twice as tall as it's wide.
+    pub grid: SizeConstraint,
+    /// Characters from the darkest to the brightest.
+    pub ramp: Vec<char>,
+    /// Swaps the ramp around, for light backgrounds.
+    pub invert: bool,
+}
+
+impl TextOutput {
+    pub fn from_value(output: &Value) -> Self {
+        Self {
+            grid: output
+                .get("grid")
+                .and_then(SizeConstraint::from_value)
+                .unwrap_or_else(ascii::default_grid),
+            ramp: parse_ramp(output, "output"),
+            invert: output
+                .get("invert")
+                .map(|v| v.as_bool().expect("[output.invert] must be a boolean."))
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// How hard PNG outputs are compressed.
+#[derive(Debug, Clone, Copy)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+impl PngCompression {
+    pub fn from_value(value: &Value) -> Self {
+        let compression = value
+            .as_str()
+            .expect("[output.compression] must be a string.");
+
+        match compression {
+            "fast" => Self::Fast,
+            "default" => Self::Default,
+            "best" => Self::Best,
+            _ => unimplemented!("png compression {compression} is not supported."),
+        }
+    }
+
+    pub fn to_compression_type(self) -> CompressionType {
+        match self {
+            Self::Fast => CompressionType::Fast,
+            Self::Default => CompressionType::Default,
+            Self::Best => CompressionType::Best,
+        }
+    }
+
+    pub fn to_png_compression(self) -> png::Compression {
+        match self {
+            Self::Fast => png::Compression::Fast,
+            Self::Default => png::Compression::Default,
+            Self::Best => png::Compression::Best,
+        }
+    }
+}
+
+/// Whether PNG outputs are saved with a palette (an indexed PNG) rather than as RGBA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexedMode {
+    /// Index the output whenever it has 256 colours or less - like after a palette dither.
+    Auto,
+    /// Like `Auto`, but warn when there are too many colours to index.
+    Always,
+    Never,
+}
+
+impl IndexedMode {
+    pub fn from_value(value: &Value) -> Self {
+        match value {
+            Value::Bool(true) => Self::Always,
+            Value::Bool(false) => Self::Never,
+            Value::String(mode) => match mode.as_str() {
+                "auto" => Self::Auto,
+                "always" => Self::Always,
+                "never" => Self::Never,
+                _ => unimplemented!("indexed mode {mode} is not supported."),
+            },
+            _ => unimplemented!("[output.indexed] must be a boolean or a mode."),
+        }
+    }
+}
+
+/// What an output's name can be made up of.
+pub struct NameVars<'a> {
+    /// The name of the source, without its extension.
+    pub stem: &'a str,
+    pub i: usize,
+    pub seed: u64,
+    /// The names of the effects, joined with `+`.
+    pub effects: String,
+}
+
+/// The name given to each output, without an extension - e.g. `{stem}-{i}`.
+#[derive(Debug, Clone)]
+pub struct NameTemplate {
+    template: String,
+}
+
+impl NameTemplate {
+    const PLACEHOLDERS: [&'static str; 5] = ["stem", "i", "seed", "date", "effects"];
+
+    pub fn new(template: impl ToString) -> Self {
+        let template = template.to_string();
+        let placeholder = Regex::new(r"\{([^}]*)\}").unwrap();
+
+        for captures in placeholder.captures_iter(&template) {
+            let name = &captures[1];
+            if !Self::PLACEHOLDERS.contains(&name) {
+                unimplemented!(
+                    "placeholder {{{name}}} is not supported - use one of {:?}.",
+                    Self::PLACEHOLDERS
+                )
+            }
+        }
+
+        Self { template }
+    }
+
+    pub fn from_value(value: &Value) -> Self {
+        Self::new(value.as_str().expect("[output.name] must be a string."))
+    }
+
+    pub fn render(&self, vars: &NameVars) -> String {
+        self.template
+            .replace("{stem}", vars.stem)
+            .replace("{i}", &format!("{:05}", vars.i))
+            .replace("{seed}", &vars.seed.to_string())
+            .replace("{date}", &Self::today())
+            .replace("{effects}", &vars.effects)
+    }
+
+    /// Today's date (in UTC) as `YYYY-MM-DD`.
+    fn today() -> String {
+        let days = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            / 86400;
+
+        Self::civil_date(days)
+    }
+
+    /// Converts days since the epoch to a `YYYY-MM-DD` date - see
+    /// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    fn civil_date(days: i64) -> String {
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        Self::new("{i}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> NameVars<'static> {
+        NameVars {
+            stem: "cat",
+            i: 7,
+            seed: 1234,
+            effects: "hue+contrast".into(),
+        }
+    }
+
+    #[test]
+    fn render_fills_every_placeholder() {
+        let name = NameTemplate::new("{stem}-{i}-{seed}-{effects}").render(&vars());
+        assert_eq!(name, "cat-00007-1234-hue+contrast");
+
+        let date = NameTemplate::new("{date}").render(&vars());
+        assert!(Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap().is_match(&date));
+    }
+
+    #[test]
+    fn render_keeps_plain_text() {
+        assert_eq!(NameTemplate::new("output").render(&vars()), "output");
+        assert_eq!(NameTemplate::default().render(&vars()), "00007");
+    }
+
+    #[test]
+    #[should_panic(expected = "placeholder {size} is not supported")]
+    fn unknown_placeholders_are_rejected() {
+        NameTemplate::new("{stem}-{size}");
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(NameTemplate::civil_date(0), "1970-01-01");
+        assert_eq!(NameTemplate::civil_date(-1), "1969-12-31");
+        assert_eq!(NameTemplate::civil_date(11016), "2000-02-29");
+        assert_eq!(NameTemplate::civil_date(20745), "2026-10-19");
+        assert_eq!(NameTemplate::civil_date(-25508), "1900-03-01");
+    }
+}
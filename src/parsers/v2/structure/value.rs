@@ -1,11 +1,48 @@
-use rand::{seq::IndexedRandom, Rng};
+use std::cell::RefCell;
+
+use rand::{rngs::StdRng, seq::IndexedRandom, Rng, RngCore, SeedableRng};
 use serde_yaml::Value;
 
+use crate::utils::pipeline::blend::BlendMode;
+
+thread_local! {
+    static ITERATION_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+}
+
+/// Seeds the RNG used to generate an iteration on this thread, so the same seed
+/// always generates the same values.
+pub fn seed_iteration(seed: u64) {
+    ITERATION_RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// The RNG of the iteration being generated on this thread.
+pub fn iteration_rng() -> IterationRng {
+    IterationRng
+}
+
+/// A handle to the thread's iteration RNG - see [`seed_iteration`].
+pub struct IterationRng;
+
+impl RngCore for IterationRng {
+    fn next_u32(&mut self) -> u32 {
+        ITERATION_RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        ITERATION_RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dst: &mut [u8]) {
+        ITERATION_RNG.with(|rng| rng.borrow_mut().fill_bytes(dst))
+    }
+}
+
 trait _Value {}
 
 impl _Value for usize {}
 impl _Value for isize {}
 impl _Value for f64 {}
+impl _Value for BlendMode {}
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub enum ValueProperty<T: _Value> {
@@ -17,6 +54,7 @@ pub enum ValueProperty<T: _Value> {
 pub type Vf64 = ValueProperty<f64>;
 pub type Vusize = ValueProperty<usize>;
 pub type Visize = ValueProperty<isize>;
+pub type VBlend = ValueProperty<BlendMode>;
 
 impl<T: _Value> From<T> for ValueProperty<T> {
     fn from(value: T) -> Self {
@@ -57,8 +95,8 @@ impl ValueProperty<usize> {
     pub fn generate(&self) -> usize {
         match self {
             ValueProperty::Fixed(val) => *val,
-            ValueProperty::Choice(vals) => *vals.choose(&mut rand::rng()).unwrap(),
-            ValueProperty::Range(min, max) => rand::rng().random_range(*min..*max),
+            ValueProperty::Choice(vals) => *vals.choose(&mut iteration_rng()).unwrap(),
+            ValueProperty::Range(min, max) => iteration_rng().random_range(*min..*max),
         }
     }
 }
@@ -96,8 +134,10 @@ impl ValueProperty<isize> {
     pub fn generate(&self) -> isize {
         match self {
             ValueProperty::Fixed(val) => *val,
-            ValueProperty::Choice(vals) => *vals.choose(&mut rand::rng()).unwrap(),
-            ValueProperty::Range(min, max) => todo!("currently can't form a range over isize"),
+            ValueProperty::Choice(vals) => *vals.choose(&mut iteration_rng()).unwrap(),
+            ValueProperty::Range(min, max) => {
+                iteration_rng().random_range(*min as i64..*max as i64) as isize
+            }
         }
     }
 }
@@ -135,8 +175,27 @@ impl ValueProperty<f64> {
     pub fn generate(&self) -> f64 {
         match self {
             ValueProperty::Fixed(val) => *val,
-            ValueProperty::Choice(vals) => *vals.choose(&mut rand::rng()).unwrap(),
-            ValueProperty::Range(min, max) => rand::rng().random_range(*min..*max),
+            ValueProperty::Choice(vals) => *vals.choose(&mut iteration_rng()).unwrap(),
+            ValueProperty::Range(min, max) => iteration_rng().random_range(*min..*max),
+        }
+    }
+}
+
+/// Blend modes can't be ranges - only a mode, or a list to pick one from.
+impl ValueProperty<BlendMode> {
+    pub fn property(value: &Value) -> Self {
+        if let Some(options) = value.as_sequence() {
+            ValueProperty::Choice(options.iter().map(BlendMode::from_value).collect())
+        } else {
+            ValueProperty::Fixed(BlendMode::from_value(value))
+        }
+    }
+
+    pub fn generate(&self) -> BlendMode {
+        match self {
+            ValueProperty::Fixed(val) => *val,
+            ValueProperty::Choice(vals) => *vals.choose(&mut iteration_rng()).unwrap(),
+            ValueProperty::Range(..) => unreachable!("blend modes can't be ranges."),
         }
     }
 }
@@ -156,6 +215,21 @@ pub fn parse_property_as_f64(value: &Value, name: &str) -> Option<ValueProperty<
     value.get(name).map(|v| ValueProperty::<f64>::property(v))
 }
 
+/// Parses a colour written as hex, like `#f80`, `#ff8800` or `#ff880080`.
+pub fn parse_colour(raw: &str) -> [u8; 4] {
+    let hex = raw.trim_start_matches('#');
+    let digit = |i: usize, len: usize| {
+        u8::from_str_radix(&hex[i..i + len], 16).unwrap_or_else(|_| panic!("invalid colour: {raw}"))
+    };
+
+    match hex.len() {
+        3 => [digit(0, 1) * 17, digit(1, 1) * 17, digit(2, 1) * 17, 255],
+        6 => [digit(0, 2), digit(2, 2), digit(4, 2), 255],
+        8 => [digit(0, 2), digit(2, 2), digit(4, 2), digit(6, 2)],
+        _ => panic!("colours must be written as #rgb, #rrggbb or #rrggbbaa, not {raw}"),
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Chance {
     value: ValueProperty<f64>,
@@ -168,8 +242,48 @@ impl From<ValueProperty<f64>> for Chance {
 }
 
 impl Chance {
+    /// Whether the roll lands above the chance - i.e. `true` with a probability of
+    /// `1 - chance`. The ordered dither modifiers are built around this.
     pub fn roll(&self) -> bool {
-        let roll = rand::rng().random_range(0.0..=1.0);
+        let roll = iteration_rng().random_range(0.0..=1.0);
         self.value.generate() < roll
     }
+
+    /// Whether something with this chance of happening happens - always at `1.0`, and
+    /// never at `0.0`. Used by the effect chain's `chance`.
+    pub fn happens(&self) -> bool {
+        let roll = iteration_rng().random_range(0.0..1.0);
+        roll < self.value.generate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(chance: f64, test: fn(&Chance) -> bool) -> usize {
+        seed_iteration(1234);
+        let chance = Chance::from(ValueProperty::Fixed(chance));
+        (0..10_000).filter(|_| test(&chance)).count()
+    }
+
+    #[test]
+    fn happens_bounds() {
+        assert_eq!(count(0.0, Chance::happens), 0);
+        assert_eq!(count(1.0, Chance::happens), 10_000);
+    }
+
+    #[test]
+    fn happens_as_often_as_the_chance() {
+        let hits = count(0.25, Chance::happens);
+        assert!((2_300..2_700).contains(&hits), "{hits} out of 10000");
+    }
+
+    #[test]
+    fn roll_lands_above_the_chance() {
+        assert_eq!(count(1.0, Chance::roll), 0);
+
+        let hits = count(0.25, Chance::roll);
+        assert!((7_300..7_700).contains(&hits), "{hits} out of 10000");
+    }
 }
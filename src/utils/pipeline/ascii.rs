@@ -0,0 +1,221 @@
+use image::{imageops, DynamicImage, GenericImageView, Rgb, RgbImage, RgbaImage};
+use palette::Srgb;
+
+use crate::{
+    parsers::v2::structure::meta::{ConstraintKind, Resample, SizeConstraint},
+    utils::pipeline::halftone::closest,
+};
+
+/// Characters from the darkest to the brightest.
+pub const DEFAULT_RAMP: &str = " .:-=+*#%@";
+
+/// A 5x7 font for printable ASCII, from ` ` to `~`. Each glyph is 5 columns, with the
+/// lowest bit at the top.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+/// The size of each character on the image, spacing included.
+const CELL: (u32, u32) = (6, 8);
+
+/// How tall characters are compared to how wide they are, in a terminal.
+pub const TEXT_ASPECT: f32 = 2.0;
+
+/// The grid used when none is given - 80 characters across, at most.
+pub fn default_grid() -> SizeConstraint {
+    SizeConstraint {
+        kind: Some(ConstraintKind::MaxDim(80)),
+        multiple_of: None,
+        filter: Resample::Triangle,
+    }
+}
+
+/// Whether any character in `ramp` can't be drawn with the built-in font.
+pub fn unsupported(ramp: &[char]) -> Option<char> {
+    ramp.iter().copied().find(|c| !(' '..='~').contains(c))
+}
+
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        ' '..='~' => FONT[c as usize - ' ' as usize],
+        _ => FONT['?' as usize - ' ' as usize],
+    }
+}
+
+/// Shrinks the image down to one pixel per character, with `constraint` deciding how many
+/// there are. Rows are squashed by `aspect`, since characters are taller than they're wide.
+pub fn grid(image: &DynamicImage, constraint: &SizeConstraint, aspect: f32) -> RgbaImage {
+    let image = constraint.constrain(image.clone());
+    let (width, height) = image.dimensions();
+    let rows = ((height as f32 / aspect).round() as u32).max(1);
+
+    imageops::resize(
+        &image.to_rgba8(),
+        width,
+        rows,
+        constraint.filter.to_filter_type(),
+    )
+}
+
+/// The character for a colour, going by its luminance.
+pub fn character(ramp: &[char], [r, g, b]: [u8; 3], invert: bool) -> char {
+    let luma = (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0;
+    let luma = if invert { 1.0 - luma } else { luma };
+    let index = (luma * ramp.len() as f32) as usize;
+
+    ramp[index.min(ramp.len() - 1)]
+}
+
+/// How the characters are coloured.
+pub enum Ink {
+    /// Each character takes the colour of what it covers.
+    Source,
+    Fixed([u8; 3]),
+}
+
+/// Renders the image as characters, drawn with a built-in bitmap font.
+pub struct Ascii {
+    pub grid: SizeConstraint,
+    pub ramp: Vec<char>,
+    /// Swaps the ramp around, for dark characters on a light background.
+    pub invert: bool,
+    pub ink: Ink,
+    pub background: [u8; 3],
+    /// If given, every character is drawn in its closest colour.
+    pub palette: Option<Vec<Srgb>>,
+    /// How many pixels each pixel of the font takes up.
+    pub scale: u32,
+}
+
+impl Ascii {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let aspect = CELL.1 as f32 / CELL.0 as f32;
+        let cells = grid(&image, &self.grid, aspect);
+        let scale = self.scale.max(1);
+        let (width, height) = (cells.width() * CELL.0, cells.height() * CELL.1);
+
+        let result = RgbImage::from_fn(width * scale, height * scale, |x, y| {
+            let (x, y) = (x / scale, y / scale);
+            let [r, g, b, a] = cells.get_pixel(x / CELL.0, y / CELL.1).0;
+            let (gx, gy) = (x % CELL.0, y % CELL.1);
+
+            // see-through cells are left blank.
+            if a < 128 || gx >= 5 || gy >= 7 {
+                return Rgb(self.background);
+            }
+
+            let c = character(&self.ramp, [r, g, b], self.invert);
+            if glyph(c)[gx as usize] >> gy & 1 == 0 {
+                return Rgb(self.background);
+            }
+
+            let ink = match self.ink {
+                Ink::Source => [r, g, b],
+                Ink::Fixed(ink) => ink,
+            };
+            match &self.palette {
+                Some(palette) => {
+                    let colour = closest(palette, ink.map(|c| c as f32 / 255.0));
+                    Rgb(colour.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+                }
+                None => Rgb(ink),
+            }
+        });
+
+        DynamicImage::ImageRgb8(result)
+    }
+}
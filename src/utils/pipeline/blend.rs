@@ -0,0 +1,244 @@
+use image::{DynamicImage, GenericImageView, GrayImage};
+use serde_yaml::Value;
+
+/// How a step's result is combined with the image it was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Difference,
+    Lighten,
+    Darken,
+    /// The result's hue, with the original's saturation and luminosity.
+    Hue,
+    /// The result's hue and saturation, with the original's luminosity.
+    Color,
+    /// The result's luminosity, with the original's hue and saturation.
+    Luminosity,
+}
+
+impl BlendMode {
+    pub fn from_value(value: &Value) -> Self {
+        let mode = value.as_str().expect("[blend] must be a string.");
+
+        match mode {
+            "normal" => Self::Normal,
+            "multiply" => Self::Multiply,
+            "screen" => Self::Screen,
+            "overlay" => Self::Overlay,
+            "difference" => Self::Difference,
+            "lighten" => Self::Lighten,
+            "darken" => Self::Darken,
+            "hue" => Self::Hue,
+            "color" | "colour" => Self::Color,
+            "luminosity" => Self::Luminosity,
+            _ => unimplemented!("blend mode {mode} is not supported."),
+        }
+    }
+
+    /// Blends two colours, with every channel from 0 to 1.
+    ///
+    /// Follows the W3C compositing spec, so these match what image editors do.
+    pub fn blend(&self, base: [f32; 3], top: [f32; 3]) -> [f32; 3] {
+        let separable =
+            |f: fn(f32, f32) -> f32| [f(base[0], top[0]), f(base[1], top[1]), f(base[2], top[2])];
+
+        match self {
+            Self::Normal => top,
+            Self::Multiply => separable(|b, t| b * t),
+            Self::Screen => separable(screen),
+            Self::Overlay => separable(|b, t| hard_light(t, b)),
+            Self::Difference => separable(|b, t| (b - t).abs()),
+            Self::Lighten => separable(f32::max),
+            Self::Darken => separable(f32::min),
+            Self::Hue => set_lum(set_sat(top, sat(base)), lum(base)),
+            Self::Color => set_lum(top, lum(base)),
+            Self::Luminosity => set_lum(base, lum(top)),
+        }
+    }
+}
+
+fn screen(base: f32, top: f32) -> f32 {
+    base + top - base * top
+}
+
+fn hard_light(base: f32, top: f32) -> f32 {
+    if top <= 0.5 {
+        base * 2.0 * top
+    } else {
+        screen(base, 2.0 * top - 1.0)
+    }
+}
+
+fn lum([r, g, b]: [f32; 3]) -> f32 {
+    0.3 * r + 0.59 * g + 0.11 * b
+}
+
+fn sat([r, g, b]: [f32; 3]) -> f32 {
+    r.max(g).max(b) - r.min(g).min(b)
+}
+
+/// Pulls a colour back into range, keeping its luminosity.
+fn clip_colour(colour: [f32; 3]) -> [f32; 3] {
+    let l = lum(colour);
+    let min = colour[0].min(colour[1]).min(colour[2]);
+    let max = colour[0].max(colour[1]).max(colour[2]);
+
+    colour.map(|c| {
+        let mut c = c;
+        if min < 0.0 {
+            c = l + (c - l) * l / (l - min);
+        }
+        if max > 1.0 {
+            c = l + (c - l) * (1.0 - l) / (max - l);
+        }
+        c
+    })
+}
+
+fn set_lum(colour: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(colour);
+    clip_colour(colour.map(|c| c + d))
+}
+
+fn set_sat(colour: [f32; 3], s: f32) -> [f32; 3] {
+    let min = colour[0].min(colour[1]).min(colour[2]);
+    let max = colour[0].max(colour[1]).max(colour[2]);
+
+    if max == min {
+        return [0.0; 3];
+    }
+
+    colour.map(|c| (c - min) * s / (max - min))
+}
+
+/// Combines `result` with the `original` it came from.
+///
+/// `opacity` and `mask` both scale how much of the blended result is kept. Anything
+/// that changed size can't be lined up with the original, so is kept as-is.
+pub fn composite(
+    original: DynamicImage,
+    result: DynamicImage,
+    mode: BlendMode,
+    opacity: f64,
+    mask: Option<&GrayImage>,
+) -> DynamicImage {
+    if original.dimensions() != result.dimensions() {
+        return result;
+    }
+
+    let has_alpha = original.color().has_alpha() || result.color().has_alpha();
+    let original = original.to_rgba8();
+    let mut result = result.to_rgba8();
+    let opacity = opacity.clamp(0.0, 1.0) as f32;
+
+    for (x, y, pixel) in result.enumerate_pixels_mut() {
+        let coverage = mask.map_or(1.0, |mask| mask.get_pixel(x, y).0[0] as f32 / 255.0);
+        let amount = opacity * coverage;
+        let base = original.get_pixel(x, y).0;
+
+        let unit = |c: u8| c as f32 / 255.0;
+        let blended = mode.blend(
+            [unit(base[0]), unit(base[1]), unit(base[2])],
+            [unit(pixel.0[0]), unit(pixel.0[1]), unit(pixel.0[2])],
+        );
+
+        for (c, to) in blended.iter().enumerate() {
+            let from = unit(base[c]);
+            let to = to.clamp(0.0, 1.0);
+            pixel.0[c] = ((from + (to - from) * amount) * 255.0).round() as u8;
+        }
+
+        let (from, to) = (base[3] as f32, pixel.0[3] as f32);
+        pixel.0[3] = (from + (to - from) * amount).round() as u8;
+    }
+
+    if has_alpha {
+        DynamicImage::ImageRgba8(result)
+    } else {
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(result).to_rgb8())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Luma, Rgb, RgbImage};
+
+    use super::*;
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!(
+            a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-4),
+            "{a:?} != {b:?}"
+        );
+    }
+
+    #[test]
+    fn separable_modes() {
+        let (base, top) = ([0.2, 0.5, 0.8], [0.5, 0.5, 0.25]);
+
+        assert_close(BlendMode::Normal.blend(base, top), top);
+        assert_close(BlendMode::Multiply.blend(base, top), [0.1, 0.25, 0.2]);
+        assert_close(BlendMode::Screen.blend(base, top), [0.6, 0.75, 0.85]);
+        assert_close(BlendMode::Difference.blend(base, top), [0.3, 0.0, 0.55]);
+        assert_close(BlendMode::Lighten.blend(base, top), [0.5, 0.5, 0.8]);
+        assert_close(BlendMode::Darken.blend(base, top), [0.2, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn overlay_follows_the_base() {
+        // mid-grey leaves the base alone, and black and white bases stay put.
+        assert_close(
+            BlendMode::Overlay.blend([0.25, 0.75, 0.5], [0.5; 3]),
+            [0.25, 0.75, 0.5],
+        );
+        assert_close(
+            BlendMode::Overlay.blend([0.0, 1.0, 0.0], [0.9; 3]),
+            [0.0, 1.0, 0.0],
+        );
+        assert_close(BlendMode::Overlay.blend([0.25; 3], [1.0; 3]), [0.5; 3]);
+    }
+
+    #[test]
+    fn non_separable_modes_keep_luminosity() {
+        let (base, top) = ([0.8, 0.3, 0.1], [0.1, 0.4, 0.9]);
+
+        assert!((lum(BlendMode::Color.blend(base, top)) - lum(base)).abs() < 1e-4);
+        assert!((lum(BlendMode::Hue.blend(base, top)) - lum(base)).abs() < 1e-4);
+        assert!((lum(BlendMode::Luminosity.blend(base, top)) - lum(top)).abs() < 1e-4);
+
+        // grey has no hue to keep.
+        assert_close(BlendMode::Hue.blend([0.5; 3], top), [0.5; 3]);
+    }
+
+    #[test]
+    fn composite_scales_by_opacity_and_mask() {
+        let original = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 1, Rgb([0, 0, 0])));
+        let result = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 1, Rgb([200, 200, 200])));
+        let mask = GrayImage::from_fn(2, 1, |x, _| Luma([if x == 0 { 255 } else { 0 }]));
+
+        let half = composite(
+            original.clone(),
+            result.clone(),
+            BlendMode::Normal,
+            0.5,
+            None,
+        );
+        assert_eq!(half.to_rgb8().get_pixel(0, 0).0, [100, 100, 100]);
+
+        let masked = composite(original, result, BlendMode::Normal, 1.0, Some(&mask));
+        assert_eq!(masked.to_rgb8().get_pixel(0, 0).0, [200, 200, 200]);
+        assert_eq!(masked.to_rgb8().get_pixel(1, 0).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn composite_keeps_resized_results() {
+        let original = DynamicImage::ImageRgb8(RgbImage::new(2, 2));
+        let result = DynamicImage::ImageRgb8(RgbImage::new(4, 4));
+
+        let composited = composite(original, result, BlendMode::Multiply, 1.0, None);
+        assert_eq!(composited.dimensions(), (4, 4));
+    }
+}
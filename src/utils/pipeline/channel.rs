@@ -0,0 +1,172 @@
+use image::DynamicImage;
+use palette::{Hsl, Hsv, IntoColor, Lch, Oklab, Oklch, Srgb};
+
+use crate::utils::pipeline::tone::{interpolate, map_colours};
+
+/// A colour space whose channels can be worked on separately.
+///
+/// Every channel is scaled to go from 0 to 1 - for hues, that's one full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourSpace {
+    Lch,
+    Oklab,
+    Oklch,
+    Hsv,
+    Hsl,
+    YCbCr,
+}
+
+impl ColourSpace {
+    /// The highest chroma `Lch` is scaled by - a little above what sRGB can reach.
+    const LCH_MAX_CHROMA: f32 = 150.0;
+    /// The same, for `Oklch` - and how far `a` and `b` reach either side of 0 in `Oklab`.
+    const OKLAB_MAX_CHROMA: f32 = 0.4;
+
+    /// The names of the channels, in order.
+    pub fn channels(&self) -> [&'static str; 3] {
+        match self {
+            Self::Lch | Self::Oklch => ["l", "c", "h"],
+            Self::Oklab => ["l", "a", "b"],
+            Self::Hsv => ["h", "s", "v"],
+            Self::Hsl => ["h", "s", "l"],
+            Self::YCbCr => ["y", "cb", "cr"],
+        }
+    }
+
+    fn is_hue(&self, channel: usize) -> bool {
+        match self {
+            Self::Lch | Self::Oklch => channel == 2,
+            Self::Hsv | Self::Hsl => channel == 0,
+            Self::Oklab | Self::YCbCr => false,
+        }
+    }
+
+    fn channels_of(&self, [r, g, b]: [f32; 3]) -> [f32; 3] {
+        let rgb = Srgb::new(r, g, b);
+
+        match self {
+            Self::Lch => {
+                let lch: Lch = rgb.into_color();
+                [
+                    lch.l / 100.0,
+                    lch.chroma / Self::LCH_MAX_CHROMA,
+                    lch.hue.into_positive_degrees() / 360.0,
+                ]
+            }
+            Self::Oklab => {
+                let lab: Oklab = rgb.into_color();
+                let range = Self::OKLAB_MAX_CHROMA * 2.0;
+                [lab.l, lab.a / range + 0.5, lab.b / range + 0.5]
+            }
+            Self::Oklch => {
+                let lch: Oklch = rgb.into_color();
+                [
+                    lch.l,
+                    lch.chroma / Self::OKLAB_MAX_CHROMA,
+                    lch.hue.into_positive_degrees() / 360.0,
+                ]
+            }
+            Self::Hsv => {
+                let hsv: Hsv = rgb.into_color();
+                [
+                    hsv.hue.into_positive_degrees() / 360.0,
+                    hsv.saturation,
+                    hsv.value,
+                ]
+            }
+            Self::Hsl => {
+                let hsl: Hsl = rgb.into_color();
+                [
+                    hsl.hue.into_positive_degrees() / 360.0,
+                    hsl.saturation,
+                    hsl.lightness,
+                ]
+            }
+            // full-range BT.601, as used by JPEG.
+            Self::YCbCr => [
+                0.299 * r + 0.587 * g + 0.114 * b,
+                0.5 - 0.168736 * r - 0.331264 * g + 0.5 * b,
+                0.5 + 0.5 * r - 0.418688 * g - 0.081312 * b,
+            ],
+        }
+    }
+
+    fn rgb_of(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let rgb: Srgb = match self {
+            Self::Lch => Lch::new(x * 100.0, y * Self::LCH_MAX_CHROMA, z * 360.0).into_color(),
+            Self::Oklab => {
+                let range = Self::OKLAB_MAX_CHROMA * 2.0;
+                Oklab::new(x, (y - 0.5) * range, (z - 0.5) * range).into_color()
+            }
+            Self::Oklch => Oklch::new(x, y * Self::OKLAB_MAX_CHROMA, z * 360.0).into_color(),
+            Self::Hsv => Hsv::new(x * 360.0, y, z).into_color(),
+            Self::Hsl => Hsl::new(x * 360.0, y, z).into_color(),
+            Self::YCbCr => {
+                let (cb, cr) = (y - 0.5, z - 0.5);
+                return [
+                    x + 1.402 * cr,
+                    x - 0.344136 * cb - 0.714136 * cr,
+                    x + 1.772 * cb,
+                ];
+            }
+        };
+
+        [rgb.red, rgb.green, rgb.blue]
+    }
+}
+
+/// Something done to a single channel, from 0 to 1.
+pub enum ChannelOp {
+    Add(f32),
+    Multiply(f32),
+    Clamp(f32, f32),
+    /// Maps through control points, like `curves`.
+    Curve(Vec<(f32, f32)>),
+    /// Rounds to a number of evenly spaced levels.
+    Quantize(u32),
+}
+
+impl ChannelOp {
+    fn apply(&self, value: f32, hue: bool) -> f32 {
+        let value = match self {
+            Self::Add(amount) => value + amount,
+            Self::Multiply(factor) => value * factor,
+            Self::Clamp(min, max) => value.clamp(*min, *max),
+            Self::Curve(points) => interpolate(points, value),
+            // hues go round in a circle, so the last level is the same as the first.
+            Self::Quantize(levels) if hue => {
+                let levels = (*levels).max(1) as f32;
+                (value * levels).round() / levels
+            }
+            Self::Quantize(levels) => {
+                let steps = (*levels).max(2) as f32 - 1.0;
+                (value * steps).round() / steps
+            }
+        };
+
+        if hue {
+            value.rem_euclid(1.0)
+        } else {
+            value
+        }
+    }
+}
+
+/// Converts to a colour space, runs operations on its channels in order, then converts back.
+pub struct ChannelOps {
+    pub space: ColourSpace,
+    /// The index of the channel each operation is done to, along with the operation.
+    pub ops: Vec<(usize, ChannelOp)>,
+}
+
+impl ChannelOps {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        map_colours(image, |rgb| {
+            let mut colour = self.space.channels_of(rgb);
+            for (channel, op) in &self.ops {
+                colour[*channel] = op.apply(colour[*channel], self.space.is_hue(*channel));
+            }
+            self.space.rgb_of(colour)
+        })
+    }
+}
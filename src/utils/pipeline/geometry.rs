@@ -0,0 +1,98 @@
+use image::{imageops, DynamicImage, GenericImageView, Rgba, RgbaImage};
+
+use crate::{
+    parsers::v2::structure::meta::{Resample, SizeConstraint},
+    utils::pipeline::Context,
+};
+
+/// Steps that change the size of the image, rather than its colours.
+pub enum Geometry {
+    /// Applies a size constraint mid-pipeline.
+    Resize(SizeConstraint),
+    /// Divides the dimensions by a factor, rounding up.
+    DownscaleBy(u32, Resample),
+    /// Scales back up to the size the image entered the pipeline at.
+    ///
+    /// With `nearest`, every pixel becomes an exact block - so anything done at the lower
+    /// resolution (like a dither) stays crisp.
+    UpscaleToSource(Resample),
+    Crop {
+        /// Where to crop from - centred if missing.
+        x: Option<u32>,
+        y: Option<u32>,
+        width: u32,
+        height: u32,
+    },
+    Pad {
+        top: u32,
+        right: u32,
+        bottom: u32,
+        left: u32,
+        colour: Rgba<u8>,
+    },
+}
+
+impl Geometry {
+    pub fn apply(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        match self {
+            Self::Resize(constraint) => constraint.constrain(image),
+            Self::DownscaleBy(factor, filter) => {
+                let (x, y) = image.dimensions();
+                image.resize_exact(
+                    x.div_ceil(*factor),
+                    y.div_ceil(*factor),
+                    filter.to_filter_type(),
+                )
+            }
+            Self::UpscaleToSource(filter) => {
+                Self::upscale_to(image, context.source_dimensions, *filter)
+            }
+            Self::Crop {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (w, h) = image.dimensions();
+                let width = (*width).min(w);
+                let height = (*height).min(h);
+                let x = x.unwrap_or((w - width) / 2).min(w - width);
+                let y = y.unwrap_or((h - height) / 2).min(h - height);
+
+                image.crop_imm(x, y, width, height)
+            }
+            Self::Pad {
+                top,
+                right,
+                bottom,
+                left,
+                colour,
+            } => {
+                let (w, h) = image.dimensions();
+                let mut padded = RgbaImage::from_pixel(w + left + right, h + top + bottom, *colour);
+                imageops::replace(&mut padded, &image.to_rgba8(), *left as i64, *top as i64);
+
+                DynamicImage::ImageRgba8(padded)
+            }
+        }
+    }
+
+    fn upscale_to(image: DynamicImage, (sx, sy): (u32, u32), filter: Resample) -> DynamicImage {
+        if filter != Resample::Nearest {
+            return image.resize_exact(sx, sy, filter.to_filter_type());
+        }
+
+        // scales by a whole number so each pixel turns into an even block, then crops off
+        // whatever `DownscaleBy` rounded up.
+        let (x, y) = image.dimensions();
+        let (fx, fy) = (sx.div_ceil(x).max(1), sy.div_ceil(y).max(1));
+        let scaled = image.resize_exact(x * fx, y * fy, imageops::Nearest);
+
+        let (w, h) = scaled.dimensions();
+        if (w, h) == (sx, sy) {
+            scaled
+        } else {
+            scaled.crop_imm((w - sx) / 2, (h - sy) / 2, sx, sy)
+        }
+    }
+}
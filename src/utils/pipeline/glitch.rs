@@ -0,0 +1,292 @@
+use std::{collections::BTreeMap, io::Cursor};
+
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, Rgba, RgbaImage};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::utils::pipeline::Context;
+
+/// What pixels are sorted by.
+#[derive(Debug, Clone, Copy)]
+pub enum SortKey {
+    Luma,
+    Hue,
+    Saturation,
+}
+
+impl SortKey {
+    /// The key of a pixel, from 0 to 1.
+    fn of(&self, Rgba([r, g, b, _]): &Rgba<u8>) -> f32 {
+        let (r, g, b) = (*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0);
+        let max = r.max(g).max(b);
+        let delta = max - r.min(g).min(b);
+
+        match self {
+            Self::Luma => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+            Self::Saturation if max == 0.0 => 0.0,
+            Self::Saturation => delta / max,
+            Self::Hue if delta == 0.0 => 0.0,
+            Self::Hue => {
+                let hue = if max == r {
+                    ((g - b) / delta).rem_euclid(6.0)
+                } else if max == g {
+                    (b - r) / delta + 2.0
+                } else {
+                    (r - g) / delta + 4.0
+                };
+                hue / 6.0
+            }
+        }
+    }
+}
+
+/// The format an image is encoded in to be databent - each breaks in its own way.
+#[derive(Debug, Clone, Copy)]
+pub enum DatabendFormat {
+    /// Smears and colour blocks, at the given quality.
+    Jpeg(u8),
+    /// Stray pixels.
+    Bmp,
+}
+
+/// Steps that break the image on purpose.
+pub enum Glitch {
+    /// Sorts runs of pixels along lines at an angle, where their key is from `low` to `high`.
+    PixelSort {
+        key: SortKey,
+        low: f32,
+        high: f32,
+        /// In degrees - 0 sorts rows, 90 sorts columns.
+        angle: f32,
+        reverse: bool,
+    },
+    /// Moves each channel by its own `(x, y)` offset.
+    ChannelShift {
+        offsets: [(i32, i32); 3],
+        wrap: bool,
+    },
+    /// Shifts bands of rows sideways by a random amount, each with a probability.
+    ScanlineDisplace {
+        probability: f32,
+        max_offset: u32,
+        band: u32,
+        wrap: bool,
+        seed: u64,
+    },
+    /// Encodes the image, corrupts some of its bytes, and decodes it again.
+    Databend {
+        format: DatabendFormat,
+        bytes: usize,
+        seed: u64,
+    },
+}
+
+impl Glitch {
+    /// How many times databending is tried before giving up, since corrupt files don't always decode.
+    const DATABEND_ATTEMPTS: u64 = 8;
+
+    pub fn apply(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return image;
+        }
+
+        let has_alpha = image.color().has_alpha();
+        let rgba = image.to_rgba8();
+
+        let result = match self {
+            Self::PixelSort {
+                key,
+                low,
+                high,
+                angle,
+                reverse,
+            } => pixel_sort(&rgba, *key, *low, *high, *angle, *reverse),
+            Self::ChannelShift { offsets, wrap } => RgbaImage::from_fn(width, height, |x, y| {
+                let mut pixel = *rgba.get_pixel(x, y);
+                for (c, (dx, dy)) in offsets.iter().enumerate() {
+                    let source = shift(x, *dx, width, *wrap).zip(shift(y, *dy, height, *wrap));
+                    pixel.0[c] = source.map_or(0, |(sx, sy)| rgba.get_pixel(sx, sy).0[c]);
+                }
+                pixel
+            }),
+            Self::ScanlineDisplace {
+                probability,
+                max_offset,
+                band,
+                wrap,
+                seed,
+            } => {
+                let mut rng = StdRng::seed_from_u64(context.seed(*seed));
+                let max = *max_offset as i32;
+                let offsets: Vec<i32> = (0..height.div_ceil((*band).max(1)))
+                    .map(|_| {
+                        if max > 0 && rng.random::<f32>() < *probability {
+                            rng.random_range(-max..=max)
+                        } else {
+                            0
+                        }
+                    })
+                    .collect();
+
+                RgbaImage::from_fn(width, height, |x, y| {
+                    let offset = offsets[(y / (*band).max(1)) as usize];
+                    match shift(x, offset, width, *wrap) {
+                        Some(sx) => *rgba.get_pixel(sx, y),
+                        None => Rgba([0, 0, 0, 255]),
+                    }
+                })
+            }
+            Self::Databend {
+                format,
+                bytes,
+                seed,
+            } => {
+                let seed = context.seed(*seed);
+                let attempt = (0..Self::DATABEND_ATTEMPTS)
+                    .find_map(|i| databend(&image, *format, *bytes, seed.wrapping_add(i)));
+
+                match attempt {
+                    Some(bent) => bent.to_rgba8(),
+                    None => {
+                        eprintln!(
+                            "warning: databending never produced a readable image - skipping it."
+                        );
+                        rgba
+                    }
+                }
+            }
+        };
+
+        if has_alpha {
+            DynamicImage::ImageRgba8(result)
+        } else {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(result).to_rgb8())
+        }
+    }
+}
+
+/// Where a pixel moved by `offset` came from, if it's inside the image (or wraps into it).
+fn shift(position: u32, offset: i32, size: u32, wrap: bool) -> Option<u32> {
+    let source = position as i64 - offset as i64;
+
+    if wrap {
+        Some(source.rem_euclid(size as i64) as u32)
+    } else if (0..size as i64).contains(&source) {
+        Some(source as u32)
+    } else {
+        None
+    }
+}
+
+fn pixel_sort(
+    image: &RgbaImage,
+    key: SortKey,
+    low: f32,
+    high: f32,
+    angle: f32,
+    reverse: bool,
+) -> RgbaImage {
+    let (sin, cos) = angle.to_radians().sin_cos();
+
+    // every pixel is put on the line through it at the angle, ordered by how far along it is.
+    let mut lines: BTreeMap<i64, Vec<(f32, u32, u32)>> = BTreeMap::new();
+    for (x, y, _) in image.enumerate_pixels() {
+        let (fx, fy) = (x as f32, y as f32);
+        let line = (fy * cos - fx * sin).round() as i64;
+        lines
+            .entry(line)
+            .or_default()
+            .push((fx * cos + fy * sin, x, y));
+    }
+
+    let mut sorted = image.clone();
+
+    for mut line in lines.into_values() {
+        line.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let positions: Vec<(u32, u32)> = line.into_iter().map(|(_, x, y)| (x, y)).collect();
+        let in_span = |(x, y): (u32, u32)| (low..=high).contains(&key.of(image.get_pixel(x, y)));
+
+        let mut start = 0;
+        while start < positions.len() {
+            if !in_span(positions[start]) {
+                start += 1;
+                continue;
+            }
+
+            let end = positions[start..]
+                .iter()
+                .position(|position| !in_span(*position))
+                .map_or(positions.len(), |length| start + length);
+            let span = &positions[start..end];
+
+            let mut pixels: Vec<Rgba<u8>> =
+                span.iter().map(|(x, y)| *image.get_pixel(*x, *y)).collect();
+            pixels.sort_by(|a, b| key.of(a).total_cmp(&key.of(b)));
+            if reverse {
+                pixels.reverse();
+            }
+
+            for ((x, y), pixel) in span.iter().zip(pixels) {
+                sorted.put_pixel(*x, *y, pixel);
+            }
+
+            start = end;
+        }
+    }
+
+    sorted
+}
+
+/// Encodes, corrupts and decodes the image once - `None` if the result didn't decode.
+fn databend(
+    image: &DynamicImage,
+    format: DatabendFormat,
+    bytes: usize,
+    seed: u64,
+) -> Option<DynamicImage> {
+    let (encoding, decoding) = match format {
+        DatabendFormat::Jpeg(quality) => (ImageOutputFormat::Jpeg(quality), ImageFormat::Jpeg),
+        DatabendFormat::Bmp => (ImageOutputFormat::Bmp, ImageFormat::Bmp),
+    };
+
+    let mut encoded = Vec::new();
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut Cursor::new(&mut encoded), encoding)
+        .ok()?;
+
+    // headers are left alone, or nothing would decode at all.
+    let start = match format {
+        DatabendFormat::Jpeg(_) => encoded
+            .windows(2)
+            .position(|marker| marker == [0xFF, 0xDA])
+            .map(|sos| {
+                sos + 2 + u16::from_be_bytes([encoded[sos + 2], encoded[sos + 3]]) as usize
+            })?,
+        DatabendFormat::Bmp => u32::from_le_bytes(encoded[10..14].try_into().unwrap()) as usize,
+    };
+    // the end of a JPEG is its end-of-image marker, which needs to stay.
+    let end = encoded.len() - 2;
+    if start >= end {
+        return None;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..bytes {
+        let i = rng.random_range(start..end);
+
+        if let DatabendFormat::Jpeg(_) = format {
+            // 0xFF starts a marker in JPEG data (and is followed by a stuffed 0x00 otherwise),
+            // so these are skipped to keep the structure readable.
+            if encoded[i] == 0xFF || encoded[i - 1] == 0xFF {
+                continue;
+            }
+            encoded[i] = rng.random_range(0..0xFF);
+        } else {
+            encoded[i] = rng.random();
+        }
+    }
+
+    image::load_from_memory_with_format(&encoded, decoding)
+        .ok()
+        .filter(|bent| bent.dimensions() == image.dimensions())
+}
@@ -0,0 +1,171 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, RgbaImage};
+use palette::Srgb;
+
+/// The shape of each halftone dot, which grows with how much ink its cell needs.
+#[derive(Debug, Clone, Copy)]
+pub enum DotShape {
+    Round,
+    Square,
+    /// Lines along the screen angle, rather than dots.
+    Line,
+    /// Dots stretched along the screen angle.
+    Ellipse,
+}
+
+impl DotShape {
+    /// Whether a point `(u, v)` from the centre of a cell of `size` is inked, for `coverage` from 0 to 1.
+    fn covers(&self, u: f32, v: f32, size: f32, coverage: f32) -> bool {
+        if coverage >= 1.0 {
+            return true;
+        }
+
+        // each dot's area is `coverage` of its cell's.
+        let area = coverage * size * size;
+        match self {
+            Self::Round => u * u + v * v <= area / std::f32::consts::PI,
+            Self::Square => u.abs().max(v.abs()) <= area.sqrt() * 0.5,
+            Self::Line => v.abs() <= size * 0.5 * coverage,
+            Self::Ellipse => {
+                let (a, b) = (1.6, 1.1);
+                (u / a).powi(2) + (v / b).powi(2) <= area / (std::f32::consts::PI * a * b)
+            }
+        }
+    }
+}
+
+/// A grid of dots, rotated to an angle.
+#[derive(Debug, Clone, Copy)]
+pub struct Screen {
+    /// In degrees.
+    pub angle: f32,
+    pub ink: [f32; 3],
+}
+
+/// How the colours are split into screens.
+pub enum HalftoneMode {
+    /// One screen of `ink` over `paper`, going by luminance.
+    Mono { screen: Screen, paper: [f32; 3] },
+    /// Cyan, magenta, yellow and black screens, printed over white.
+    Cmyk([Screen; 4]),
+}
+
+pub struct Halftone {
+    pub mode: HalftoneMode,
+    pub cell_size: u32,
+    pub shape: DotShape,
+    /// If given, every pixel is snapped to the closest colour in it.
+    pub palette: Option<Vec<Srgb>>,
+}
+
+impl Halftone {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = image.dimensions();
+        let has_alpha = image.color().has_alpha();
+        let source = image.to_rgba8();
+        let size = self.cell_size.max(1) as f32;
+
+        let result = RgbImage::from_fn(width, height, |x, y| {
+            let colour = match &self.mode {
+                HalftoneMode::Mono { screen, paper } => {
+                    let inked = self.inked(&source, screen.angle, x, y, size, |[r, g, b]| {
+                        1.0 - (0.2126 * r + 0.7152 * g + 0.0722 * b)
+                    });
+                    if inked {
+                        screen.ink
+                    } else {
+                        *paper
+                    }
+                }
+                HalftoneMode::Cmyk(screens) => {
+                    let mut colour = [1.0; 3];
+                    for (channel, screen) in screens.iter().enumerate() {
+                        let inked =
+                            self.inked(&source, screen.angle, x, y, size, |rgb| cmyk(rgb)[channel]);
+                        if inked {
+                            for (c, ink) in colour.iter_mut().zip(screen.ink) {
+                                *c *= ink;
+                            }
+                        }
+                    }
+                    colour
+                }
+            };
+
+            let colour = match &self.palette {
+                Some(palette) => closest(palette, colour),
+                None => colour,
+            };
+
+            Rgb(colour.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+        });
+
+        if !has_alpha {
+            return DynamicImage::ImageRgb8(result);
+        }
+
+        let mut rgba = DynamicImage::ImageRgb8(result).to_rgba8();
+        for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+            pixel.0[3] = source.get_pixel(x, y).0[3];
+        }
+        DynamicImage::ImageRgba8(rgba)
+    }
+
+    /// Whether the screen at `angle` puts ink on a pixel, with `coverage` saying how much
+    /// ink each colour needs.
+    fn inked<F>(
+        &self,
+        source: &RgbaImage,
+        angle: f32,
+        x: u32,
+        y: u32,
+        size: f32,
+        coverage: F,
+    ) -> bool
+    where
+        F: Fn([f32; 3]) -> f32,
+    {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+
+        // the pixel's position on the rotated screen, and the centre of its cell there.
+        let (u, v) = (px * cos + py * sin, -px * sin + py * cos);
+        let (cu, cv) = (
+            ((u / size).floor() + 0.5) * size,
+            ((v / size).floor() + 0.5) * size,
+        );
+
+        // the cell's ink is taken from the colour at its centre.
+        let (sx, sy) = (cu * cos - cv * sin, cu * sin + cv * cos);
+        let sx = (sx.max(0.0) as u32).min(source.width() - 1);
+        let sy = (sy.max(0.0) as u32).min(source.height() - 1);
+        let [r, g, b, _] = source.get_pixel(sx, sy).0;
+        let colour = [r, g, b].map(|c| c as f32 / 255.0);
+
+        self.shape
+            .covers(u - cu, v - cv, size, coverage(colour).clamp(0.0, 1.0))
+    }
+}
+
+/// Splits a colour into how much cyan, magenta, yellow and black ink it needs.
+fn cmyk([r, g, b]: [f32; 3]) -> [f32; 4] {
+    let k = 1.0 - r.max(g).max(b);
+    if k >= 1.0 {
+        return [0.0, 0.0, 0.0, 1.0];
+    }
+
+    let ink = |c: f32| (1.0 - c - k) / (1.0 - k);
+    [ink(r), ink(g), ink(b), k]
+}
+
+/// The colour in `palette` nearest to a colour.
+pub fn closest(palette: &[Srgb], [r, g, b]: [f32; 3]) -> [f32; 3] {
+    palette
+        .iter()
+        .min_by(|x, y| {
+            let distance =
+                |c: &Srgb| (c.red - r).powi(2) + (c.green - g).powi(2) + (c.blue - b).powi(2);
+            distance(x).total_cmp(&distance(y))
+        })
+        .map(|c| [c.red, c.green, c.blue])
+        .unwrap_or([r, g, b])
+}
@@ -0,0 +1,128 @@
+use std::sync::Arc;
+
+use image::{imageops, DynamicImage, GenericImageView, GrayImage, Luma, Rgb};
+
+/// Which part of the image a mask covers.
+pub enum MaskShape {
+    /// A rectangle, with its position and size as fractions of the image.
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    },
+    /// An ellipse, with its centre and radii as fractions of the image.
+    Ellipse { cx: f64, cy: f64, rx: f64, ry: f64 },
+    /// Pixels with a luminance between `min` and `max` (from 0 to 1).
+    Luma { min: f64, max: f64 },
+    /// Pixels with a hue between `min` and `max`, in degrees. Wraps around if `min > max`.
+    Hue { min: f64, max: f64 },
+    /// A greyscale image, stretched over the image - white is fully masked in.
+    Image(Arc<GrayImage>),
+}
+
+/// Limits where a step applies, from fully (white) to not at all (black).
+pub struct Mask {
+    pub shape: MaskShape,
+    /// How far the edges are softened, as a fraction of the image's shorter side.
+    pub feather: f64,
+    pub invert: bool,
+}
+
+impl Mask {
+    /// Works out the mask for a specific image.
+    pub fn build(&self, image: &DynamicImage) -> GrayImage {
+        let (width, height) = image.dimensions();
+
+        let mut mask = match &self.shape {
+            MaskShape::Image(mask) => {
+                imageops::resize(mask.as_ref(), width, height, imageops::Triangle)
+            }
+            MaskShape::Luma { .. } | MaskShape::Hue { .. } => {
+                let rgb = image.to_rgb8();
+                GrayImage::from_fn(width, height, |x, y| {
+                    Self::coverage(self.shape.contains_colour(rgb.get_pixel(x, y)))
+                })
+            }
+            _ => GrayImage::from_fn(width, height, |x, y| {
+                let fx = (x as f64 + 0.5) / width as f64;
+                let fy = (y as f64 + 0.5) / height as f64;
+                Self::coverage(self.shape.contains_point(fx, fy))
+            }),
+        };
+
+        if self.feather > 0.0 {
+            let sigma = self.feather * width.min(height) as f64;
+            mask = imageops::blur(&mask, sigma as f32);
+        }
+
+        if self.invert {
+            imageops::invert(&mut mask);
+        }
+
+        mask
+    }
+
+    fn coverage(inside: bool) -> Luma<u8> {
+        Luma([if inside { 255 } else { 0 }])
+    }
+}
+
+impl MaskShape {
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        match self {
+            Self::Rect {
+                x: rx,
+                y: ry,
+                width,
+                height,
+            } => x >= *rx && x < rx + width && y >= *ry && y < ry + height,
+            Self::Ellipse { cx, cy, rx, ry } => {
+                let dx = (x - cx) / rx;
+                let dy = (y - cy) / ry;
+                dx * dx + dy * dy <= 1.0
+            }
+            _ => unreachable!("only shapes have points"),
+        }
+    }
+
+    fn contains_colour(&self, Rgb([r, g, b]): &Rgb<u8>) -> bool {
+        let (r, g, b) = (*r as f64 / 255.0, *g as f64 / 255.0, *b as f64 / 255.0);
+
+        match self {
+            Self::Luma { min, max } => {
+                let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                luma >= *min && luma <= *max
+            }
+            Self::Hue { min, max } => {
+                let hue = hue_of(r, g, b);
+                if min <= max {
+                    hue >= *min && hue <= *max
+                } else {
+                    hue >= *min || hue <= *max
+                }
+            }
+            _ => unreachable!("only ranges have colours"),
+        }
+    }
+}
+
+/// The HSV hue of a colour, in degrees. Greys count as a hue of 0.
+fn hue_of(r: f64, g: f64, b: f64) -> f64 {
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+
+    if delta == 0.0 {
+        return 0.0;
+    }
+
+    let hue = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+
+    hue * 60.0
+}
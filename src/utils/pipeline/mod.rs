@@ -0,0 +1,232 @@
+use image::{DynamicImage, GenericImageView};
+use image_effects::dispatch::EffectEnum;
+
+use crate::utils::pipeline::{
+    ascii::Ascii,
+    blend::{composite, BlendMode},
+    channel::ChannelOps,
+    geometry::Geometry,
+    glitch::Glitch,
+    halftone::Halftone,
+    mask::Mask,
+    noise::Noise,
+    spatial::Spatial,
+    tone::Tone,
+};
+
+pub mod ascii;
+pub mod blend;
+pub mod channel;
+pub mod geometry;
+pub mod glitch;
+pub mod halftone;
+pub mod mask;
+pub mod noise;
+pub mod spatial;
+pub mod tone;
+
+/// A generated effect, ready to be applied.
+pub enum Step {
+    /// One of the effects provided by `image_effects`.
+    Effect(EffectEnum<DynamicImage>),
+    Geometry(Geometry),
+    Tone(Tone),
+    Spatial(Spatial),
+    Glitch(Glitch),
+    Channel(ChannelOps),
+    Halftone(Halftone),
+    Ascii(Ascii),
+    Noise(Noise),
+    /// A series of steps, applied one after another.
+    Chain(Pipeline),
+    /// Steps that are each given the same image, with their results layered on top
+    /// of it in order.
+    Branch(Vec<Layer>),
+}
+
+impl Step {
+    pub fn apply(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        match self {
+            Self::Effect(effect) => effect.affect(image),
+            Self::Geometry(geometry) => geometry.apply(image, context),
+            Self::Tone(tone) => tone.apply(image),
+            Self::Spatial(spatial) => spatial.apply(image),
+            Self::Glitch(glitch) => glitch.apply(image, context),
+            Self::Channel(channel) => channel.apply(image),
+            Self::Halftone(halftone) => halftone.apply(image),
+            Self::Ascii(ascii) => ascii.apply(image),
+            Self::Noise(noise) => noise.apply(image, context),
+            Self::Chain(pipeline) => pipeline.apply_with(image, context),
+            Self::Branch(layers) => layers.iter().fold(image.clone(), |base, layer| {
+                layer.merge(&image, base, context)
+            }),
+        }
+    }
+}
+
+impl From<EffectEnum<DynamicImage>> for Step {
+    fn from(value: EffectEnum<DynamicImage>) -> Self {
+        Self::Effect(value)
+    }
+}
+
+impl From<Geometry> for Step {
+    fn from(value: Geometry) -> Self {
+        Self::Geometry(value)
+    }
+}
+
+impl From<Tone> for Step {
+    fn from(value: Tone) -> Self {
+        Self::Tone(value)
+    }
+}
+
+impl From<Spatial> for Step {
+    fn from(value: Spatial) -> Self {
+        Self::Spatial(value)
+    }
+}
+
+impl From<Glitch> for Step {
+    fn from(value: Glitch) -> Self {
+        Self::Glitch(value)
+    }
+}
+
+impl From<ChannelOps> for Step {
+    fn from(value: ChannelOps) -> Self {
+        Self::Channel(value)
+    }
+}
+
+impl From<Halftone> for Step {
+    fn from(value: Halftone) -> Self {
+        Self::Halftone(value)
+    }
+}
+
+impl From<Ascii> for Step {
+    fn from(value: Ascii) -> Self {
+        Self::Ascii(value)
+    }
+}
+
+impl From<Noise> for Step {
+    fn from(value: Noise) -> Self {
+        Self::Noise(value)
+    }
+}
+
+/// A step, along with where in the image it applies and how it's blended back in.
+pub struct Layer {
+    pub step: Step,
+    /// The effect the step was generated from - `None` for groups of effects, which
+    /// go by the names of what's inside them.
+    pub name: Option<&'static str>,
+    pub mask: Option<Mask>,
+    pub blend: BlendMode,
+    pub opacity: f64,
+}
+
+impl Layer {
+    pub fn apply(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        if self.mask.is_none() && self.blend == BlendMode::Normal && self.opacity >= 1.0 {
+            return self.step.apply(image, context);
+        }
+
+        self.merge(&image.clone(), image, context)
+    }
+
+    fn names(&self, names: &mut Vec<&'static str>) {
+        match (self.name, &self.step) {
+            (Some(name), _) => names.push(name),
+            (None, Step::Chain(pipeline)) => {
+                pipeline.layers.iter().for_each(|layer| layer.names(names))
+            }
+            (None, Step::Branch(layers)) => layers.iter().for_each(|layer| layer.names(names)),
+            (None, _) => {}
+        }
+    }
+
+    /// Applies the step to `input`, then layers the result over `base`.
+    fn merge(&self, input: &DynamicImage, base: DynamicImage, context: &Context) -> DynamicImage {
+        // the mask is worked out from the image as it was before this step.
+        let weights = self.mask.as_ref().map(|mask| mask.build(input));
+        let result = self.step.apply(input.clone(), context);
+        composite(base, result, self.blend, self.opacity, weights.as_ref())
+    }
+}
+
+impl From<Step> for Layer {
+    fn from(step: Step) -> Self {
+        Self {
+            step,
+            name: None,
+            mask: None,
+            blend: BlendMode::Normal,
+            opacity: 1.0,
+        }
+    }
+}
+
+/// What steps can know about the image before the pipeline started.
+pub struct Context {
+    /// The dimensions of the image going into the pipeline.
+    pub source_dimensions: (u32, u32),
+    /// Which frame of an animation this is - always `0` for still images.
+    pub frame: u64,
+}
+
+impl Context {
+    /// Mixes the frame into a step's seed, so random steps change from frame to frame
+    /// instead of repeating the same pattern. The first frame keeps the seed as-is.
+    pub fn seed(&self, seed: u64) -> u64 {
+        seed ^ self.frame.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+/// The generated effects of an iteration, applied in order.
+pub struct Pipeline {
+    layers: Vec<Layer>,
+}
+
+impl Pipeline {
+    pub fn new(layers: Vec<Layer>) -> Self {
+        Self { layers }
+    }
+
+    /// The names of the effects that were generated, in order.
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        self.layers.iter().for_each(|layer| layer.names(&mut names));
+        names
+    }
+
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        self.apply_to_frame(image, 0)
+    }
+
+    /// Applies the pipeline to one frame of an animation.
+    pub fn apply_to_frame(&self, image: DynamicImage, frame: u64) -> DynamicImage {
+        let context = Context {
+            source_dimensions: image.dimensions(),
+            frame,
+        };
+
+        self.apply_with(image, &context)
+    }
+
+    /// Applies the pipeline as part of a larger one.
+    pub fn apply_with(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        self.layers
+            .iter()
+            .fold(image, |image, layer| layer.apply(image, context))
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
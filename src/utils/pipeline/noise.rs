@@ -0,0 +1,237 @@
+use std::f32::consts::TAU;
+
+use image::{DynamicImage, GenericImageView};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::utils::pipeline::{tone::map_pixels, Context};
+
+/// Kinds of noise, with every `amount` on the same 0 to 1 scale as the channels.
+pub enum NoiseKind {
+    /// Luma-only grain, in clumps about `size` pixels across.
+    FilmGrain { amount: f32, size: f32 },
+    /// Adds gaussian noise with a standard deviation of `amount` - to every channel
+    /// separately, unless `mono`.
+    Gaussian { amount: f32, mono: bool },
+    /// Turns `amount` of the pixels black or white.
+    SaltAndPepper { amount: f32 },
+    /// Smooth gradient noise, as a grey field to blend over the image. `scale` is the size
+    /// of its features in pixels, and each octave adds detail at half the size.
+    Perlin {
+        scale: f32,
+        octaves: u32,
+        amount: f32,
+    },
+    /// Like `Perlin`, with fewer straight-line artifacts.
+    Simplex {
+        scale: f32,
+        octaves: u32,
+        amount: f32,
+    },
+}
+
+pub struct Noise {
+    pub kind: NoiseKind,
+    /// Mixed with the frame being processed, so animations don't get the same noise on
+    /// every frame.
+    pub seed: u64,
+}
+
+impl Noise {
+    pub fn apply(&self, image: DynamicImage, context: &Context) -> DynamicImage {
+        let mut rng = StdRng::seed_from_u64(context.seed(self.seed));
+
+        match self.kind {
+            NoiseKind::FilmGrain { amount, size } => {
+                let grain = Grain::new(&mut rng, image.dimensions(), size.max(1.0));
+                map_pixels(image, |x, y, rgb| {
+                    let delta = grain.at(x, y) * amount;
+                    rgb.map(|c| c + delta)
+                })
+            }
+            NoiseKind::Gaussian { amount, mono } => map_pixels(image, |_, _, rgb| {
+                if mono {
+                    let delta = gaussian(&mut rng) * amount;
+                    rgb.map(|c| c + delta)
+                } else {
+                    rgb.map(|c| c + gaussian(&mut rng) * amount)
+                }
+            }),
+            NoiseKind::SaltAndPepper { amount } => map_pixels(image, |_, _, rgb| {
+                if rng.random::<f32>() >= amount {
+                    rgb
+                } else if rng.random() {
+                    [1.0; 3]
+                } else {
+                    [0.0; 3]
+                }
+            }),
+            NoiseKind::Perlin {
+                scale,
+                octaves,
+                amount,
+            }
+            | NoiseKind::Simplex {
+                scale,
+                octaves,
+                amount,
+            } => {
+                let field = Field::new(&mut rng);
+                let simplex = matches!(self.kind, NoiseKind::Simplex { .. });
+                let scale = scale.max(1.0);
+
+                map_pixels(image, |x, y, _| {
+                    let (x, y) = (x as f32 / scale, y as f32 / scale);
+                    let n = field.fbm(x, y, octaves.max(1), simplex);
+                    [0.5 + 0.5 * n * amount; 3]
+                })
+            }
+        }
+    }
+}
+
+/// A sample from the standard normal distribution, using the Box-Muller transform.
+fn gaussian(rng: &mut StdRng) -> f32 {
+    let u: f32 = rng.random::<f32>().max(f32::MIN_POSITIVE);
+    let v: f32 = rng.random();
+
+    (-2.0 * u.ln()).sqrt() * (TAU * v).cos()
+}
+
+/// Gaussian noise on a coarse grid, smoothly interpolated between its points.
+struct Grain {
+    values: Vec<f32>,
+    width: usize,
+    size: f32,
+}
+
+impl Grain {
+    fn new(rng: &mut StdRng, (width, height): (u32, u32), size: f32) -> Self {
+        let width = (width as f32 / size).ceil() as usize + 2;
+        let height = (height as f32 / size).ceil() as usize + 2;
+
+        Self {
+            values: (0..width * height).map(|_| gaussian(rng)).collect(),
+            width,
+            size,
+        }
+    }
+
+    fn at(&self, x: u32, y: u32) -> f32 {
+        let (x, y) = (x as f32 / self.size, y as f32 / self.size);
+        let (x0, y0) = (x as usize, y as usize);
+        let (fx, fy) = (x.fract(), y.fract());
+        let value = |x: usize, y: usize| self.values[y * self.width + x];
+
+        let top = value(x0, y0) * (1.0 - fx) + value(x0 + 1, y0) * fx;
+        let bottom = value(x0, y0 + 1) * (1.0 - fx) + value(x0 + 1, y0 + 1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// A shuffled table of gradients, shared by perlin and simplex noise.
+struct Field {
+    permutation: [usize; 512],
+}
+
+impl Field {
+    const GRADIENTS: [(f32, f32); 8] = [
+        (1.0, 1.0),
+        (-1.0, 1.0),
+        (1.0, -1.0),
+        (-1.0, -1.0),
+        (1.0, 0.0),
+        (-1.0, 0.0),
+        (0.0, 1.0),
+        (0.0, -1.0),
+    ];
+
+    fn new(rng: &mut StdRng) -> Self {
+        let mut table: Vec<usize> = (0..256).collect();
+        table.shuffle(rng);
+
+        Self {
+            permutation: std::array::from_fn(|i| table[i % 256]),
+        }
+    }
+
+    /// The dot product of the gradient at a lattice point with an offset from it.
+    fn gradient(&self, i: i32, j: i32, x: f32, y: f32) -> f32 {
+        let hash = self.permutation[self.permutation[(i & 255) as usize] + (j & 255) as usize];
+        let (gx, gy) = Self::GRADIENTS[hash % 8];
+        gx * x + gy * y
+    }
+
+    fn perlin(&self, x: f32, y: f32) -> f32 {
+        let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        let (i, j) = (x.floor() as i32, y.floor() as i32);
+        let (x, y) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(x), fade(y));
+
+        let top = lerp(
+            self.gradient(i, j, x, y),
+            self.gradient(i + 1, j, x - 1.0, y),
+            u,
+        );
+        let bottom = lerp(
+            self.gradient(i, j + 1, x, y - 1.0),
+            self.gradient(i + 1, j + 1, x - 1.0, y - 1.0),
+            u,
+        );
+        lerp(top, bottom, v)
+    }
+
+    fn simplex(&self, x: f32, y: f32) -> f32 {
+        let f2 = 0.5 * (3f32.sqrt() - 1.0);
+        let g2 = (3.0 - 3f32.sqrt()) / 6.0;
+
+        // which simplex (triangle) the point is in, and its first corner.
+        let s = (x + y) * f2;
+        let (i, j) = ((x + s).floor() as i32, (y + s).floor() as i32);
+        let t = (i + j) as f32 * g2;
+        let (x0, y0) = (x - (i as f32 - t), y - (j as f32 - t));
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+
+        let corners = [
+            (i, j, x0, y0),
+            (i + i1, j + j1, x0 - i1 as f32 + g2, y0 - j1 as f32 + g2),
+            (i + 1, j + 1, x0 - 1.0 + 2.0 * g2, y0 - 1.0 + 2.0 * g2),
+        ];
+
+        let n: f32 = corners
+            .into_iter()
+            .map(|(i, j, x, y)| {
+                let t = 0.5 - x * x - y * y;
+                if t < 0.0 {
+                    0.0
+                } else {
+                    t.powi(4) * self.gradient(i, j, x, y)
+                }
+            })
+            .sum();
+
+        // scales the result to roughly -1 to 1.
+        70.0 * n
+    }
+
+    /// Adds up octaves of noise, each at twice the frequency and half the strength.
+    fn fbm(&self, x: f32, y: f32, octaves: u32, simplex: bool) -> f32 {
+        let (mut total, mut strength, mut frequency, mut max) = (0.0, 1.0, 1.0, 0.0);
+
+        for _ in 0..octaves {
+            let (x, y) = (x * frequency, y * frequency);
+            total += strength
+                * if simplex {
+                    self.simplex(x, y)
+                } else {
+                    self.perlin(x, y)
+                };
+            max += strength;
+            strength *= 0.5;
+            frequency *= 2.0;
+        }
+
+        total / max
+    }
+}
@@ -0,0 +1,301 @@
+use image::{DynamicImage, GenericImageView, RgbImage, RgbaImage};
+use rayon::prelude::*;
+
+/// Steps that look at each pixel's neighbours, through a convolution kernel.
+pub enum Spatial {
+    GaussianBlur(f32),
+    /// Averages every pixel within a radius.
+    BoxBlur(u32),
+    /// Pushes each pixel away from its blurred self, where they differ by more than `threshold`.
+    UnsharpMask {
+        sigma: f32,
+        amount: f32,
+        threshold: f32,
+    },
+    /// The edges of the image in white, using the Sobel operator.
+    EdgeDetect,
+    Emboss(f32),
+    Convolve(Kernel),
+}
+
+/// A grid of weights, centred over each pixel.
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    weights: Vec<f32>,
+    /// Added to every result, with channels from 0 to 1.
+    bias: f32,
+}
+
+impl Kernel {
+    /// A kernel from its rows, which all need the same length. Every weight is divided by
+    /// `divisor` - or by the sum of the weights, if not given and it isn't 0.
+    pub fn new(rows: Vec<Vec<f32>>, divisor: Option<f32>, bias: f32) -> Self {
+        let height = rows.len();
+        let width = rows.first().map(Vec::len).unwrap_or(0);
+
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            panic!("kernels need at least one weight, with every row the same length.");
+        }
+
+        let weights: Vec<f32> = rows.into_iter().flatten().collect();
+        let divisor = divisor.unwrap_or_else(|| {
+            let sum: f32 = weights.iter().sum();
+            if sum == 0.0 {
+                1.0
+            } else {
+                sum
+            }
+        });
+
+        Self {
+            width,
+            height,
+            weights: weights.into_iter().map(|w| w / divisor).collect(),
+            bias,
+        }
+    }
+
+    fn horizontal(weights: Vec<f32>) -> Self {
+        Self::new(vec![weights], None, 0.0)
+    }
+
+    fn vertical(weights: Vec<f32>) -> Self {
+        Self::new(weights.into_iter().map(|w| vec![w]).collect(), None, 0.0)
+    }
+
+    fn gaussian(sigma: f32) -> Vec<f32> {
+        let sigma = sigma.max(0.01);
+        let radius = (sigma * 3.0).ceil() as i32;
+        (-radius..=radius)
+            .map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+            .collect()
+    }
+}
+
+/// An image's colour channels as floats, so kernels can go out of range between passes.
+struct Buffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 3]>,
+}
+
+impl Buffer {
+    fn from_image(image: &DynamicImage) -> Self {
+        let rgb = image.to_rgb32f();
+
+        Self {
+            width: rgb.width() as usize,
+            height: rgb.height() as usize,
+            pixels: rgb.pixels().map(|pixel| pixel.0).collect(),
+        }
+    }
+
+    /// Turns the buffer back into an image, with the alpha of `original` if it had any.
+    fn into_image(self, original: &DynamicImage) -> DynamicImage {
+        let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let (width, height) = (self.width as u32, self.height as u32);
+
+        if original.color().has_alpha() {
+            let alpha = original.to_rgba8();
+            DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+                let [r, g, b] = self.pixels[y as usize * self.width + x as usize];
+                image::Rgba([byte(r), byte(g), byte(b), alpha.get_pixel(x, y).0[3]])
+            }))
+        } else {
+            DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| {
+                image::Rgb(self.pixels[y as usize * self.width + x as usize].map(byte))
+            }))
+        }
+    }
+
+    /// Runs a kernel over every pixel, a row per task. Edges are extended outwards.
+    fn convolve(&self, kernel: &Kernel) -> Self {
+        let (cx, cy) = ((kernel.width / 2) as isize, (kernel.height / 2) as isize);
+        let mut pixels = vec![[0.0; 3]; self.pixels.len()];
+
+        pixels
+            .par_chunks_mut(self.width)
+            .enumerate()
+            .for_each(|(y, row)| {
+                for (x, pixel) in row.iter_mut().enumerate() {
+                    let mut sum = [kernel.bias; 3];
+
+                    for ky in 0..kernel.height {
+                        let sy = (y as isize + ky as isize - cy).clamp(0, self.height as isize - 1);
+                        for kx in 0..kernel.width {
+                            let sx =
+                                (x as isize + kx as isize - cx).clamp(0, self.width as isize - 1);
+                            let weight = kernel.weights[ky * kernel.width + kx];
+                            let source = self.pixels[sy as usize * self.width + sx as usize];
+
+                            for (total, value) in sum.iter_mut().zip(source) {
+                                *total += value * weight;
+                            }
+                        }
+                    }
+
+                    *pixel = sum;
+                }
+            });
+
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Convolves with a kernel that's the same horizontally and vertically, in two passes.
+    fn separable(&self, weights: Vec<f32>) -> Self {
+        self.convolve(&Kernel::horizontal(weights.clone()))
+            .convolve(&Kernel::vertical(weights))
+    }
+
+    fn zip(&self, other: &Self, f: impl Fn(f32, f32) -> f32 + Sync) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .par_iter()
+                .zip(&other.pixels)
+                .map(|(a, b)| [f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2])])
+                .collect(),
+        }
+    }
+}
+
+impl Spatial {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        if image.dimensions().0 == 0 || image.dimensions().1 == 0 {
+            return image;
+        }
+
+        let buffer = Buffer::from_image(&image);
+
+        let result = match self {
+            Self::GaussianBlur(sigma) => buffer.separable(Kernel::gaussian(*sigma)),
+            Self::BoxBlur(radius) => buffer.separable(vec![1.0; *radius as usize * 2 + 1]),
+            Self::UnsharpMask {
+                sigma,
+                amount,
+                threshold,
+            } => {
+                let blurred = buffer.separable(Kernel::gaussian(*sigma));
+                buffer.zip(&blurred, |original, blurred| {
+                    let detail = original - blurred;
+                    if detail.abs() < *threshold {
+                        original
+                    } else {
+                        original + detail * amount
+                    }
+                })
+            }
+            Self::EdgeDetect => {
+                let gx = Kernel::new(
+                    vec![
+                        vec![-1.0, 0.0, 1.0],
+                        vec![-2.0, 0.0, 2.0],
+                        vec![-1.0, 0.0, 1.0],
+                    ],
+                    Some(1.0),
+                    0.0,
+                );
+                let gy = Kernel::new(
+                    vec![
+                        vec![-1.0, -2.0, -1.0],
+                        vec![0.0, 0.0, 0.0],
+                        vec![1.0, 2.0, 1.0],
+                    ],
+                    Some(1.0),
+                    0.0,
+                );
+
+                // edges are found in the luminance, so every channel agrees on them.
+                let luma = Buffer {
+                    pixels: buffer
+                        .pixels
+                        .iter()
+                        .map(|[r, g, b]| [0.2126 * r + 0.7152 * g + 0.0722 * b; 3])
+                        .collect(),
+                    ..buffer
+                };
+
+                luma.convolve(&gx)
+                    .zip(&luma.convolve(&gy), |x, y| (x * x + y * y).sqrt())
+            }
+            Self::Emboss(strength) => buffer.convolve(&Kernel::new(
+                vec![
+                    vec![-strength, -strength, 0.0],
+                    vec![-strength, 1.0, *strength],
+                    vec![0.0, *strength, *strength],
+                ],
+                Some(1.0),
+                0.0,
+            )),
+            Self::Convolve(kernel) => buffer.convolve(kernel),
+        };
+
+        result.into_image(&image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum(kernel: &Kernel) -> f32 {
+        kernel.weights.iter().sum()
+    }
+
+    #[test]
+    fn weights_are_divided_by_their_sum() {
+        let kernel = Kernel::new(vec![vec![1.0, 2.0, 1.0], vec![2.0, 4.0, 2.0]], None, 0.0);
+
+        assert_eq!((kernel.width, kernel.height), (3, 2));
+        assert!((sum(&kernel) - 1.0).abs() < 1e-6);
+        assert!((kernel.weights[4] - 4.0 / 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn divisor_overrides_the_sum() {
+        let kernel = Kernel::new(vec![vec![1.0, 1.0], vec![1.0, 1.0]], Some(2.0), 0.5);
+
+        assert_eq!(kernel.weights, vec![0.5; 4]);
+        assert_eq!(kernel.bias, 0.5);
+    }
+
+    #[test]
+    fn zero_sum_kernels_are_left_alone() {
+        let edges = vec![
+            vec![-1.0, -1.0, -1.0],
+            vec![-1.0, 8.0, -1.0],
+            vec![-1.0, -1.0, -1.0],
+        ];
+        let kernel = Kernel::new(edges.clone(), None, 0.0);
+
+        assert_eq!(kernel.weights, edges.concat());
+    }
+
+    #[test]
+    fn gaussians_are_symmetric_and_normalised() {
+        let kernel = Kernel::horizontal(Kernel::gaussian(1.5));
+
+        assert_eq!(kernel.width, 11);
+        assert!((sum(&kernel) - 1.0).abs() < 1e-5);
+        assert!(kernel.weights.iter().eq(kernel.weights.iter().rev()));
+    }
+
+    #[test]
+    #[should_panic(expected = "every row the same length")]
+    fn ragged_rows_are_rejected() {
+        Kernel::new(vec![vec![1.0, 1.0], vec![1.0]], None, 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one weight")]
+    fn empty_kernels_are_rejected() {
+        Kernel::new(vec![], None, 0.0);
+    }
+}
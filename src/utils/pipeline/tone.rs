@@ -0,0 +1,126 @@
+use image::DynamicImage;
+
+/// Steps that remap the colours of each pixel on their own, without looking at its neighbours.
+pub enum Tone {
+    /// Mixes the channels together using these weights, which are normalised to add up to 1.
+    Grayscale([f32; 3]),
+    /// Rounds each channel to a number of evenly spaced levels.
+    Posterize([u32; 3]),
+    /// Makes every pixel black or white, depending on whether its luminance reaches the level.
+    Threshold(f32),
+    /// Stretches `black`-`white` out to the full range, then applies a gamma.
+    Levels { black: f32, white: f32, gamma: f32 },
+    /// Maps each channel through control points from 0 to 1, interpolated linearly.
+    Curves([Vec<(f32, f32)>; 3]),
+}
+
+impl Tone {
+    pub fn apply(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Self::Grayscale(weights) => {
+                let total: f32 = weights.iter().sum();
+                let weights = weights.map(|w| w / total.max(f32::EPSILON));
+                map_colours(image, |[r, g, b]| {
+                    let luma = r * weights[0] + g * weights[1] + b * weights[2];
+                    [luma; 3]
+                })
+            }
+            Self::Threshold(level) => map_colours(image, |[r, g, b]| {
+                let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                [if luma >= *level { 1.0 } else { 0.0 }; 3]
+            }),
+            _ => {
+                let luts = self.luts();
+                map_colours(image, |colour| {
+                    std::array::from_fn(|c| luts[c][(colour[c] * 255.0).round() as usize])
+                })
+            }
+        }
+    }
+
+    /// Precomputes a lookup table for each channel, for the steps that treat channels separately.
+    fn luts(&self) -> [[f32; 256]; 3] {
+        let mut luts = [[0.0; 256]; 3];
+
+        for (c, lut) in luts.iter_mut().enumerate() {
+            for (i, value) in lut.iter_mut().enumerate() {
+                let v = i as f32 / 255.0;
+
+                *value = match self {
+                    Self::Posterize(levels) => {
+                        let steps = levels[c].max(2) as f32 - 1.0;
+                        (v * steps).round() / steps
+                    }
+                    Self::Levels {
+                        black,
+                        white,
+                        gamma,
+                    } => {
+                        let range = (white - black).max(f32::EPSILON);
+                        ((v - black) / range).clamp(0.0, 1.0).powf(1.0 / gamma)
+                    }
+                    Self::Curves(curves) => interpolate(&curves[c], v),
+                    _ => unreachable!("only per-channel steps have lookup tables"),
+                };
+            }
+        }
+
+        luts
+    }
+}
+
+/// Follows the line through `points` (sorted by x) at `x`, flat past either end.
+pub fn interpolate(points: &[(f32, f32)], x: f32) -> f32 {
+    match points {
+        [] => x,
+        [(_, y)] => *y,
+        _ => {
+            let (first, last) = (points[0], points[points.len() - 1]);
+            if x <= first.0 {
+                return first.1;
+            }
+            if x >= last.0 {
+                return last.1;
+            }
+
+            let i = points.iter().position(|(px, _)| *px > x).unwrap();
+            let ((x0, y0), (x1, y1)) = (points[i - 1], points[i]);
+            y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+        }
+    }
+}
+
+/// Maps the colour of every pixel, with channels from 0 to 1, leaving alpha alone.
+pub fn map_colours<F>(image: DynamicImage, f: F) -> DynamicImage
+where
+    F: Fn([f32; 3]) -> [f32; 3],
+{
+    map_pixels(image, |_, _, colour| f(colour))
+}
+
+/// Like `map_colours`, but also given where each pixel is - in order, row by row.
+pub fn map_pixels<F>(image: DynamicImage, mut f: F) -> DynamicImage
+where
+    F: FnMut(u32, u32, [f32; 3]) -> [f32; 3],
+{
+    let unit = |c: u8| c as f32 / 255.0;
+    let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+    if image.color().has_alpha() {
+        let mut rgba = image.to_rgba8();
+        for (x, y, pixel) in rgba.enumerate_pixels_mut() {
+            let [r, g, b, a] = pixel.0;
+            let [r, g, b] = f(x, y, [unit(r), unit(g), unit(b)]);
+            pixel.0 = [byte(r), byte(g), byte(b), a];
+        }
+        DynamicImage::ImageRgba8(rgba)
+    } else {
+        let mut rgb = image.to_rgb8();
+        for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+            let [r, g, b] = pixel.0;
+            let [r, g, b] = f(x, y, [unit(r), unit(g), unit(b)]);
+            pixel.0 = [byte(r), byte(g), byte(b)];
+        }
+        DynamicImage::ImageRgb8(rgb)
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+use reqwest::{
+    blocking::{Client, Response},
+    header::{CONTENT_TYPE, RETRY_AFTER},
+    StatusCode,
+};
+
+use crate::parsers::v2::structure::fetch::FetchConfig;
+
+/// The longest a `Retry-After` header is allowed to hold up a download.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// The longest the backoff is allowed to double up to, unless it starts out longer.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    /// Everything fetched during this run, so a URL is only ever downloaded once.
+    static ref FETCHED: Mutex<HashMap<String, Fetched>> = Mutex::new(HashMap::new());
+}
+
+/// The contents of a URL.
+#[derive(Clone)]
+pub struct Fetched {
+    pub bytes: Arc<Vec<u8>>,
+    pub content_type: Option<String>,
+}
+
+pub struct Fetcher {}
+
+impl Fetcher {
+    /// Fetches a URL, checking this run's downloads and then the on-disk cache first.
+    pub fn fetch(url: &str, config: &FetchConfig) -> Fetched {
+        if let Some(fetched) = FETCHED.lock().unwrap().get(url) {
+            return fetched.clone();
+        }
+
+        let fetched = match Self::read_cache(url, config) {
+            Some(fetched) => fetched,
+            None => {
+                let fetched = Self::download(url, config);
+                Self::write_cache(url, config, &fetched);
+                fetched
+            }
+        };
+
+        FETCHED
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), fetched.clone());
+
+        fetched
+    }
+
+    fn download(url: &str, config: &FetchConfig) -> Fetched {
+        let client = Client::builder().timeout(config.timeout).build().unwrap();
+        let attempts = config.retries + 1;
+
+        for attempt in 1..=attempts {
+            let response = client.get(url).send();
+            let retry_after = response.as_ref().ok().and_then(Self::retry_after);
+            let response = response.and_then(|response| response.error_for_status());
+
+            let error = match response {
+                Ok(response) => {
+                    let content_type = response
+                        .headers()
+                        .get(CONTENT_TYPE)
+                        .and_then(|v| v.to_str().ok())
+                        .map(|v| v.to_string());
+
+                    match response.bytes() {
+                        Ok(bytes) => {
+                            return Fetched {
+                                bytes: Arc::new(bytes.to_vec()),
+                                content_type,
+                            }
+                        }
+                        Err(error) => error,
+                    }
+                }
+                Err(error) => error,
+            };
+
+            // client errors won't go away by asking again - unless we're told to slow down.
+            let is_client_error = error
+                .status()
+                .is_some_and(|s| s.is_client_error() && s != StatusCode::TOO_MANY_REQUESTS);
+
+            if is_client_error || attempt == attempts {
+                panic!("failed to fetch [{url}] after {attempt} attempt(s): {error}");
+            }
+
+            let wait = retry_after.unwrap_or_else(|| Self::backoff(config, attempt));
+            eprintln!("[ ! ] | Fetching [{url}] failed ({error}), retrying in {wait:?}...");
+            std::thread::sleep(wait);
+        }
+
+        unreachable!("every attempt either returns or panics")
+    }
+
+    /// How long to wait after the `attempt`th failure, when the server doesn't say.
+    fn backoff(config: &FetchConfig, attempt: usize) -> Duration {
+        let factor = 2u32.saturating_pow(attempt as u32 - 1);
+        config
+            .backoff
+            .saturating_mul(factor)
+            .min(config.backoff.max(MAX_BACKOFF))
+    }
+
+    /// How long the server asked to wait before trying again, if it did. Only the
+    /// number-of-seconds form of `Retry-After` is understood.
+    fn retry_after(response: &Response) -> Option<Duration> {
+        let seconds: u64 = response
+            .headers()
+            .get(RETRY_AFTER)?
+            .to_str()
+            .ok()?
+            .trim()
+            .parse()
+            .ok()?;
+
+        Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+    }
+
+    /// Where a URL is cached, without an extension.
+    fn cache_path(url: &str, config: &FetchConfig) -> Option<String> {
+        config
+            .cache
+            .as_ref()
+            .map(|dir| format!("{dir}/{:016x}", fnv1a(url.as_bytes())))
+    }
+
+    fn read_cache(url: &str, config: &FetchConfig) -> Option<Fetched> {
+        let path = Self::cache_path(url, config)?;
+
+        // the URL is kept with each entry, so a hash collision is just a cache miss.
+        if std::fs::read_to_string(format!("{path}.url")).ok()? != url {
+            return None;
+        }
+
+        let bytes = std::fs::read(format!("{path}.bin")).ok()?;
+        let content_type = std::fs::read_to_string(format!("{path}.type")).ok();
+
+        Some(Fetched {
+            bytes: Arc::new(bytes),
+            content_type: content_type.filter(|v| !v.is_empty()),
+        })
+    }
+
+    /// Failing to cache doesn't stop the source from being used, so errors are only reported.
+    fn write_cache(url: &str, config: &FetchConfig, fetched: &Fetched) {
+        let Some(path) = Self::cache_path(url, config) else {
+            return;
+        };
+
+        let written = std::fs::create_dir_all(config.cache.as_ref().unwrap())
+            .and_then(|_| std::fs::write(format!("{path}.url"), url))
+            .and_then(|_| {
+                std::fs::write(
+                    format!("{path}.type"),
+                    fetched.content_type.as_deref().unwrap_or(""),
+                )
+            })
+            // the bytes go last, since they're what marks an entry as complete.
+            .and_then(|_| std::fs::write(format!("{path}.bin"), fetched.bytes.as_slice()));
+
+        if let Err(error) = written {
+            eprintln!("[ ! ] | Couldn't cache [{url}]: {error}");
+        }
+    }
+}
+
+/// 64-bit FNV-1a - unlike `DefaultHasher`, it hashes the same way on every run and build,
+/// so cache entries stay valid.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        panic::{self, AssertUnwindSafe},
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Instant,
+    };
+
+    use super::*;
+
+    const OK: &str = "HTTP/1.1 200 OK\r\nContent-Type: image/png\r\nContent-Length: 5\r\n\r\nhello";
+    const UNAVAILABLE: &str = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n";
+    const NOT_FOUND: &str = "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n";
+    const RATE_LIMITED: &str =
+        "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 1\r\nContent-Length: 0\r\n\r\n";
+
+    /// A stand-in server that answers each connection with the next response (repeating
+    /// the last one), returning its URL and how many requests it's had.
+    fn serve(responses: &[&'static str]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let responses = responses.to_vec();
+
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let n = stream.read(&mut buf).unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+
+                let i = counter.fetch_add(1, Ordering::SeqCst);
+                let response = responses[i.min(responses.len() - 1)];
+                let response = response.replacen("\r\n", "\r\nConnection: close\r\n", 1);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn config(cache: Option<String>) -> FetchConfig {
+        FetchConfig {
+            timeout: Duration::from_secs(5),
+            retries: 2,
+            backoff: Duration::from_millis(50),
+            cache,
+            rewrites: vec![],
+        }
+    }
+
+    fn fails(url: &str, config: &FetchConfig) -> bool {
+        panic::catch_unwind(AssertUnwindSafe(|| Fetcher::download(url, config))).is_err()
+    }
+
+    #[test]
+    fn cache_hit_skips_the_download() {
+        let (url, requests) = serve(&[OK]);
+        let dir = std::env::temp_dir().join(format!("imgtoy-fetch-{}", fnv1a(url.as_bytes())));
+        let config = config(Some(dir.display().to_string()));
+
+        let fetched = Fetcher::fetch(&url, &config);
+        assert_eq!(fetched.bytes.as_slice(), b"hello");
+
+        // forget this run's download, so only the on-disk cache is left.
+        FETCHED.lock().unwrap().remove(&url);
+        let cached = Fetcher::fetch(&url, &config);
+
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        assert_eq!(cached.bytes.as_slice(), b"hello");
+        assert_eq!(cached.content_type.as_deref(), Some("image/png"));
+
+        let path = Fetcher::cache_path(&url, &config).unwrap();
+        assert_eq!(std::fs::read_to_string(format!("{path}.url")).unwrap(), url);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn retries_with_backoff() {
+        let (url, requests) = serve(&[UNAVAILABLE, UNAVAILABLE, OK]);
+
+        let start = Instant::now();
+        let fetched = Fetcher::download(&url, &config(None));
+
+        assert_eq!(fetched.bytes.as_slice(), b"hello");
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+        // 50ms, then 100ms.
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn backoff_stops_doubling() {
+        let config = config(None);
+
+        assert_eq!(Fetcher::backoff(&config, 1), Duration::from_millis(50));
+        assert_eq!(Fetcher::backoff(&config, 3), Duration::from_millis(200));
+        // 2^40 would overflow a u32.
+        assert_eq!(Fetcher::backoff(&config, 41), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn gives_up_after_every_attempt() {
+        let (url, requests) = serve(&[UNAVAILABLE]);
+
+        assert!(fails(&url, &config(None)));
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        let (url, requests) = serve(&[NOT_FOUND]);
+
+        assert!(fails(&url, &config(None)));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn rate_limits_wait_for_retry_after() {
+        let (url, requests) = serve(&[RATE_LIMITED, OK]);
+
+        let start = Instant::now();
+        let fetched = Fetcher::download(&url, &config(None));
+
+        assert_eq!(fetched.bytes.as_slice(), b"hello");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn fnv1a_is_stable() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
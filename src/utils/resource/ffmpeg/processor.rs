@@ -1,46 +1,95 @@
-use image::{DynamicImage, GenericImageView};
-use image_effects::dispatch::EffectEnum;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
+
+use image::DynamicImage;
 
 use crate::{
-    parsers::v2::structure::meta::{SizeConstraint, SourceKind},
-    utils::resource::ffmpeg::utils::{FfmpegPathUtil, FfmpegUtil},
-    utils::resource::image::parser::ImageKind,
+    parsers::v2::structure::{
+        alpha::AlphaConfig,
+        ffmpeg::FfmpegConfig,
+        meta::{AudioMode, Output, SizeConstraint, Source, SourceKind, Trim},
+    },
+    utils::pipeline::Pipeline,
+    utils::resource::ffmpeg::utils::{FfmpegPathUtil, FfmpegUtil, VideoInfo},
+    utils::resource::image::parser::{ImageKind, ImageParser},
 };
 
+/// Processes animated media by streaming frames through ffmpeg.
+///
+/// Nothing is decoded up-front - constraints and effects are recorded, then applied
+/// frame-by-frame once the result is saved.
 #[derive(Clone)]
 pub struct FfmpegProcessor {
     source: String,
     image_kind: ImageKind,
-    frames: Vec<DynamicImage>,
-    fps: f64,
+    info: VideoInfo,
+    constraint: Option<SizeConstraint>,
+    trim: Trim,
+    effects: Arc<Pipeline>,
+    alpha: AlphaConfig,
+    settings: FfmpegConfig,
+    /// Where the source's audio was extracted to, if it had any.
+    audio: Option<String>,
     prefix: String,
 }
 
 impl FfmpegProcessor {
-    pub fn use_source(source: &SourceKind) -> Self {
+    pub fn use_source(source: &Source, settings: &FfmpegConfig) -> Self {
+        let trim = source.trim.clone();
+        let source = &source.kind;
         let image_kind = source.get_image_kind();
-        let source_path = source.get_path();
-        let prefix = "source";
+        let prefix = Self::source_prefix(&source.get_path());
+        let path_util = Self::_gen_path_util(&prefix);
+
+        // ffmpeg gets a local file to work with, even if the source is on the web.
+        let source_path = match source {
+            SourceKind::File(path) => path.clone(),
+            SourceKind::Url(..) | SourceKind::Stdin => {
+                ImageParser::write_temp(&ImageParser::read_bytes(source), &path_util)
+            }
+        };
 
-        let (frames, fps) = match image_kind {
-            ImageKind::Image => todo!(),
-            ImageKind::Gif | ImageKind::Anim => {
-                Self::process_animated(&source_path, Self::_gen_path_util(prefix))
+        let (info, audio) = match image_kind {
+            // `Resource` hands still images to `ImageResource` instead.
+            ImageKind::Image => panic!(
+                "[{}] is a still image - ffmpeg is only used for animations and videos.",
+                source.get_path()
+            ),
+            ImageKind::Gif | ImageKind::Apng | ImageKind::AnimWebp | ImageKind::Anim => {
+                Self::process_animated(&source_path, image_kind, path_util)
             }
         };
 
-        println!("DETECTED {fps}fps");
+        eprintln!(
+            "DETECTED {}fps ({}/{})",
+            info.fps(),
+            info.frame_rate.num,
+            info.frame_rate.den
+        );
 
         Self {
             source: source_path,
             image_kind,
-            frames,
-            fps,
-            prefix: prefix.into(),
+            info,
+            constraint: None,
+            trim,
+            effects: Arc::new(Pipeline::default()),
+            alpha: AlphaConfig::default(),
+            settings: settings.clone(),
+            audio,
+            prefix,
         }
     }
 
+    /// A temp prefix unique to this source, so concurrent runs don't share extracted files.
+    fn source_prefix(source_path: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+        source_path.hash(&mut hasher);
+        format!("source-{}-{:016x}", std::process::id(), hasher.finish())
+    }
+
     pub fn gen_path_util(&self) -> FfmpegPathUtil {
         Self::_gen_path_util(&self.prefix)
     }
@@ -52,12 +101,13 @@ impl FfmpegProcessor {
         }
     }
 
+    /// How many frames are held in memory at each stage of the stream.
+    pub fn frame_window() -> usize {
+        rayon::current_num_threads() * 2
+    }
+
     pub fn constrain(mut self, constrain: &SizeConstraint) -> Self {
-        self.frames = self
-            .frames
-            .into_iter()
-            .map(|frame| constrain.constrain(frame))
-            .collect();
+        self.constraint = Some(constrain.clone());
         self
     }
 
@@ -65,40 +115,89 @@ impl FfmpegProcessor {
         self.prefix = prefix.to_string();
     }
 
-    pub fn save(&self, out: &str) {
-        let extension = match self.image_kind {
+    pub fn extension(&self) -> &'static str {
+        match self.image_kind {
             ImageKind::Gif => "gif",
+            ImageKind::Apng => "apng",
+            ImageKind::AnimWebp => "webp",
             ImageKind::Anim => "mp4",
             ImageKind::Image => panic!("yeah something went v. weird here"),
+        }
+    }
+
+    pub fn save(&self, path: &str, output: &Output) {
+        let audio = match output.audio {
+            AudioMode::Keep => self.audio.as_deref(),
+            AudioMode::Drop => None,
         };
 
-        FfmpegUtil::combine_media(
-            &self.frames,
-            self.fps,
-            self.gen_path_util(),
-            format!("{out}.{extension}").as_str(),
+        let frames = FfmpegUtil::process_media(
+            &self.source,
+            self.image_kind,
+            &self.info,
+            audio,
+            &self.settings,
+            &self.trim,
+            self.alpha.skip_transparent,
+            path,
+            Self::frame_window(),
+            |index, frame| self.process_frame(index, frame),
         );
+
+        eprintln!("[ @ ] | [{path}] encoded {frames} frames.");
     }
 
-    fn process_animated(path: &str, prefix: FfmpegPathUtil) -> (Vec<DynamicImage>, f64) {
-        FfmpegUtil::split_media(path, prefix)
+    fn process_animated(
+        path: &str,
+        image_kind: ImageKind,
+        prefix: FfmpegPathUtil,
+    ) -> (VideoInfo, Option<String>) {
+        match image_kind {
+            ImageKind::Anim => {
+                let audio = FfmpegUtil::extract_audio(path, prefix);
+                (FfmpegUtil::get_video_info(path), audio)
+            }
+            // animated images store a delay per frame, which ffmpeg's reported rate
+            // doesn't capture - and ffmpeg can't read animated WebPs at all.
+            _ => {
+                let bytes = std::fs::read(path).unwrap();
+                let delays = ImageParser::frame_delays(&bytes, image_kind);
+                let (width, height) = ImageParser::animation_dimensions(&bytes, image_kind);
+
+                let info = VideoInfo {
+                    frame_rate: FfmpegUtil::frame_rate_from_delays(&delays),
+                    width,
+                    height,
+                };
+
+                (info, None)
+            }
+        }
     }
 
-    pub fn apply_effects(mut self, effects: Vec<EffectEnum<DynamicImage>>) -> Self {
-        for effect in effects.into_iter() {
-            self.frames = self
-                .frames
-                .into_par_iter()
-                .map(|frame| effect.affect(frame))
-                .collect();
+    fn process_frame(&self, index: usize, mut frame: DynamicImage) -> DynamicImage {
+        if let Some(constraint) = &self.constraint {
+            frame = constraint.constrain(frame);
         }
 
+        self.alpha.preserve(frame, |frame| {
+            self.effects.apply_to_frame(frame, index as u64)
+        })
+    }
+
+    pub fn apply_effects(mut self, effects: Pipeline, alpha: &AlphaConfig) -> Self {
+        self.effects = Arc::new(effects);
+        self.alpha = alpha.clone();
         self
     }
 
     pub fn get_dimensions(&self) -> (u32, u32) {
-        println!("WE HAVE {} frames", self.frames.len());
-        self.frames.get(0).unwrap().dimensions()
+        let dims = (self.info.width, self.info.height);
+
+        match &self.constraint {
+            Some(constraint) => constraint.constrain_dimensions(dims),
+            None => dims,
+        }
     }
 
     pub fn clear_temp(&self) {
@@ -1,10 +1,36 @@
-use std::path::Path;
+use std::{
+    path::Path,
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread::JoinHandle,
+};
 
-use ez_ffmpeg::{stream_info::StreamInfo, FfmpegContext, FfmpegScheduler, Input, Output};
-use image::DynamicImage;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use ez_ffmpeg::{
+    core::context::output::VSyncMethod, stream_info::StreamInfo, AVRational, FfmpegContext,
+    FfmpegScheduler, Input, Output,
+};
+use image::{DynamicImage, RgbaImage};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 
-use crate::utils::resource::image::parser::ImageParser;
+use crate::{
+    parsers::v2::structure::{ffmpeg::FfmpegConfig, meta::Trim},
+    utils::resource::image::parser::{ImageKind, ImageParser},
+};
+
+/// `AVERROR_EOF` - returned from a read callback to tell ffmpeg the stream has ended.
+const AVERROR_EOF: i32 = -0x2046_4F45;
+
+/// Returned from a write callback to tell ffmpeg that nobody is listening anymore.
+const AVERROR_EXIT: i32 = -0x5449_5845;
+
+/// Label of the filter output that decoded frames are read from.
+const FRAMES_LABEL: &str = "frames";
+
+/// Label of the filter output that gets encoded into the final file.
+const ENCODED_LABEL: &str = "encoded";
+
+/// The shortest delay animated images are played with. GIF delays are in hundredths of a
+/// second, and browsers slow down any frame shorter than this.
+pub const MIN_DELAY_STEP_MS: u32 = 20;
 
 pub struct FfmpegUtil {}
 
@@ -17,66 +43,143 @@ impl FfmpegUtil {
         panic!("END OF FFMPEG DEMO");
     }
 
-    pub fn split_media(input: &str, ffmpeg_path: FfmpegPathUtil) -> (Vec<DynamicImage>, f64) {
-        let VideoInfo { fps, frame_count } = Self::get_video_info(input);
-        Self::split_media_into_frames(input, ffmpeg_path.clone());
+    /// Streams every frame of `input` through `transform`, encoding the results into `out`.
+    /// `transform` is also given the index of each frame. `skip_transparent` is passed on to
+    /// the palette of GIF outputs.
+    ///
+    /// Frames never touch the disk - they're piped to and from ffmpeg as raw RGBA, and at most
+    /// `window` frames are held at each stage, so memory use doesn't grow with the video length.
+    ///
+    /// Returns how many frames were processed - panicking if there weren't any.
+    pub fn process_media<F>(
+        input: &str,
+        image_kind: ImageKind,
+        info: &VideoInfo,
+        audio: Option<&str>,
+        settings: &FfmpegConfig,
+        trim: &Trim,
+        skip_transparent: bool,
+        out: &str,
+        window: usize,
+        transform: F,
+    ) -> usize
+    where
+        F: Fn(usize, DynamicImage) -> DynamicImage + Sync,
+    {
+        let (frame_tx, frame_rx) = mpsc::sync_channel(window);
+        let decoder = match image_kind {
+            ImageKind::AnimWebp => ImageParser::split_animation_into_frames(
+                input,
+                image_kind,
+                Self::step_ms(info.frame_rate),
+                trim.clone(),
+                frame_tx,
+            ),
+            _ => Self::split_media_into_frames(
+                input,
+                (info.width, info.height),
+                info.frame_rate,
+                trim.clone(),
+                settings.hwaccel.clone(),
+                frame_tx,
+            ),
+        };
 
-        // let mut frames = Vec::with_capacity(frame_count as usize);
+        let frame_rate = Self::stepped_frame_rate(info.frame_rate, trim.step());
+        let audio = audio.map(|path| (path, Self::audio_window(trim, frame_rate)));
 
-        (
-            (1..frame_count + 1)
+        let mut encoder: Option<(SyncSender<Vec<u8>>, JoinHandle<()>)> = None;
+        let mut batch = Vec::with_capacity(window);
+        let mut frame_count = 0;
+
+        'stream: loop {
+            batch.extend(frame_rx.iter().take(window));
+            if batch.is_empty() {
+                break;
+            }
+
+            // every frame before this batch has been sent, so they're counted already.
+            let processed: Vec<RgbaImage> = std::mem::take(&mut batch)
                 .into_par_iter()
-                .map(|i| {
-                    let image_name = ffmpeg_path.frame_path_rs(i);
-                    let frame = ImageParser::parse_localfile(&image_name).into_image();
-                    // frames.push(frame);
-                    frame
+                .enumerate()
+                .map(|(i, frame)| {
+                    transform(frame_count + i, DynamicImage::ImageRgba8(frame)).into_rgba8()
                 })
-                .collect(),
-            fps,
-        )
-    }
+                .collect();
+
+            // the encoder is only started once the first frame is out, since effects
+            // are free to change the dimensions.
+            let (out_tx, _) = encoder.get_or_insert_with(|| {
+                let (out_tx, out_rx) = mpsc::sync_channel(window);
+                let handle = Self::combine_frames_into_file(
+                    out_rx,
+                    processed[0].dimensions(),
+                    frame_rate,
+                    settings.clone(),
+                    skip_transparent,
+                    out,
+                    audio,
+                );
+                (out_tx, handle)
+            });
+
+            for frame in processed {
+                if out_tx.send(frame.into_raw()).is_err() {
+                    break 'stream;
+                }
+                frame_count += 1;
+            }
+        }
 
-    pub fn combine_media(
-        frames: &Vec<DynamicImage>,
-        frame_rate: f64,
-        path_util: FfmpegPathUtil,
-        out: &str,
-    ) {
-        let dir = path_util.dir();
-        if !Path::new(&dir).is_dir() {
-            std::fs::create_dir_all(&dir).unwrap();
+        // dropping the receiver unblocks the decoder if we stopped early.
+        drop(frame_rx);
+        decoder.join().unwrap();
+
+        if let Some((out_tx, handle)) = encoder {
+            drop(out_tx);
+            handle.join().unwrap();
         }
 
-        frames.iter().enumerate().for_each(|(i, frame)| {
-            let i = i + 1;
-            frame.save(path_util.frame_path_rs(i as i64)).unwrap()
-        });
+        // the encoder never started, so there's no file to speak of.
+        if frame_count == 0 {
+            panic!("no frames were decoded from [{input}] - is it trimmed past its end?");
+        }
 
-        Self::combine_frames_into_file(path_util, out, frame_rate, true);
+        frame_count
     }
 
     pub fn clear_temp(temp_prefix: &str) {
-        std::fs::remove_dir_all(format!("temp/{temp_prefix}")).unwrap();
+        let dir = format!("temp/{temp_prefix}");
+        if Path::new(&dir).is_dir() {
+            std::fs::remove_dir_all(dir).unwrap();
+        }
     }
 
-    // accepts gif, images, etc...
-    pub fn split_media_into_frames(input: &str, temp_prefix: FfmpegPathUtil) {
-        // ffmpeg -i input/Clap.gif -vsync 0 temp/temp%d.png
+    /// Extracts the audio track of `input` so it can be muxed back in later, returning
+    /// where it was written to.
+    ///
+    /// The track is copied as-is, and nothing is extracted if `input` has no audio.
+    pub fn extract_audio(input: &str, temp_prefix: FfmpegPathUtil) -> Option<String> {
+        if !Self::has_audio(input) {
+            return None;
+        }
+
         let temp_dir = temp_prefix.dir();
         if !Path::new(&temp_dir).is_dir() {
             std::fs::create_dir_all(&temp_dir).unwrap();
         }
 
-        let context =
-            FfmpegContext::builder()
-                .input(Input::from(input).set_hwaccel("cuda"))
-                .output(Output::from(temp_prefix.frame_path()).set_vsync_method(
-                    ez_ffmpeg::core::context::output::VSyncMethod::VsyncPassthrough,
-                ))
-                // .output(Output::from(temp_prefix.audio_path()))
-                .build()
-                .unwrap();
+        let audio_path = temp_prefix.audio_path();
+
+        let context = FfmpegContext::builder()
+            .input(Input::from(input))
+            .output(
+                Output::from(audio_path.as_str())
+                    .add_stream_map("0:a:0")
+                    .set_audio_codec("copy"),
+            )
+            .build()
+            .unwrap();
 
         FfmpegScheduler::new(context)
             .start()
@@ -84,12 +187,23 @@ impl FfmpegUtil {
             .wait()
             .unwrap();
 
+        Some(audio_path)
+    }
+
+    /// Encodes a still image as AVIF.
+    ///
+    /// `quality` goes from 1 to 100, like JPEG, and is mapped onto the encoder's CRF.
+    pub fn encode_avif(input: &str, out: &str, quality: Option<u8>) {
+        let crf = 63 - (quality.unwrap_or(75) as u32 * 63 / 100);
+
         let context = FfmpegContext::builder()
-            .input(Input::from(input).set_hwaccel("cuda"))
-            // .output(Output::from(temp_prefix.frame_path()).set_vsync_method(
-            //     ez_ffmpeg::core::context::output::VSyncMethod::VsyncPassthrough,
-            // ))
-            .output(Output::from(temp_prefix.audio_path()))
+            .input(Input::from(input))
+            .output(
+                Output::from(out)
+                    .set_video_codec("libaom-av1")
+                    .set_video_codec_opt("still-picture", "1")
+                    .set_video_codec_opt("crf", crf.to_string()),
+            )
             .build()
             .unwrap();
 
@@ -100,60 +214,342 @@ impl FfmpegUtil {
             .unwrap();
     }
 
+    pub fn has_audio(input: &str) -> bool {
+        matches!(
+            ez_ffmpeg::stream_info::find_audio_stream_info(input),
+            Ok(Some(_))
+        )
+    }
+
+    /// The audio codec to use for a given output, based on its container.
+    ///
+    /// Returns `None` for containers that can't hold audio at all.
+    pub fn audio_codec_for(out: &str) -> Option<&'static str> {
+        let extension = out.rsplit('.').next().unwrap_or_default();
+
+        match extension {
+            "mp4" | "mov" | "m4v" => Some("aac"),
+            "webm" => Some("libopus"),
+            "mkv" => Some("copy"),
+            "avi" => Some("libmp3lame"),
+            _ => None,
+        }
+    }
+
+    /// Decodes `input` on a separate thread, sending each frame as it's decoded.
+    ///
+    /// Frames come out at a constant `frame_rate` - ffmpeg duplicates or drops frames of
+    /// variable-frame-rate sources to keep their timing - and only from the part of the
+    /// source selected by `trim`. Since the channel is bounded, decoding pauses whenever
+    /// the receiver falls behind.
+    pub fn split_media_into_frames(
+        input: &str,
+        (width, height): (u32, u32),
+        frame_rate: AVRational,
+        trim: Trim,
+        hwaccel: Option<String>,
+        frames: SyncSender<RgbaImage>,
+    ) -> JoinHandle<()> {
+        let input = input.to_string();
+        let frame_size = (width * height * 4) as usize;
+
+        std::thread::spawn(move || {
+            let mut buffer = Vec::with_capacity(frame_size);
+            let step = trim.step();
+
+            let output = Output::new_by_write_callback(move |bytes: &[u8]| {
+                let mut remaining = bytes;
+
+                while !remaining.is_empty() {
+                    let needed = frame_size - buffer.len();
+                    let (head, tail) = remaining.split_at(needed.min(remaining.len()));
+                    buffer.extend_from_slice(head);
+                    remaining = tail;
+
+                    if buffer.len() == frame_size {
+                        let raw = std::mem::replace(&mut buffer, Vec::with_capacity(frame_size));
+                        let frame = RgbaImage::from_raw(width, height, raw).unwrap();
+
+                        if frames.send(frame).is_err() {
+                            return AVERROR_EXIT;
+                        }
+                    }
+                }
+
+                bytes.len() as i32
+            })
+            .set_format("rawvideo")
+            .set_video_codec("rawvideo")
+            .set_vsync_method(VSyncMethod::VsyncCfr)
+            .set_framerate(Self::stepped_frame_rate(frame_rate, step))
+            .add_stream_map(FRAMES_LABEL);
+
+            let mut input = Input::from(input.as_str());
+            if let Some(hwaccel) = hwaccel {
+                input = input.set_hwaccel(hwaccel);
+            }
+            if let Some(start) = trim.start {
+                input = input.set_start_time_us(Self::to_us(start));
+            }
+            if let Some(time) = trim.recording_time() {
+                input = input.set_recording_time_us(Self::to_us(time));
+            }
+
+            let output = match trim.max_frames {
+                Some(max_frames) => output.set_max_video_frames(max_frames as i64),
+                None => output,
+            };
+
+            // frames are brought to a constant rate first, so that every nth frame is
+            // evenly spaced even for variable-frame-rate sources.
+            let filter = if step > 1 {
+                format!(
+                    "[0:v]fps={}/{},select=not(mod(n\\,{step})),format=rgba[{FRAMES_LABEL}]",
+                    frame_rate.num, frame_rate.den
+                )
+            } else {
+                format!("[0:v]format=rgba[{FRAMES_LABEL}]")
+            };
+
+            let context = FfmpegContext::builder()
+                .input(input)
+                .filter_desc(filter)
+                .output(output)
+                .build()
+                .unwrap();
+
+            FfmpegScheduler::new(context)
+                .start()
+                .unwrap()
+                .wait()
+                .unwrap();
+        })
+    }
+
+    /// Encodes frames into `out` on a separate thread as they arrive.
+    ///
+    /// Each frame is expected to be raw RGBA of the given dimensions. Encoding finishes once
+    /// every sender has been dropped.
     pub fn combine_frames_into_file(
-        ffmpeg_path: FfmpegPathUtil,
+        frames: Receiver<Vec<u8>>,
+        (width, height): (u32, u32),
+        frame_rate: AVRational,
+        settings: FfmpegConfig,
+        skip_transparent: bool,
         out: &str,
-        frame_rate: f64,
-        audio: bool,
-    ) {
-        // ffmpeg -f image2 -framerate 9 -i image_%003d.jpg -vf scale=531x299,transpose=1,crop=299,431,0,100 out.gif
-        let mut context = FfmpegContext::builder()
-            .input(Input::from(ffmpeg_path.frame_path()).set_format("image2"))
-            // .filter_descs(vec![
-            //     "scale=X.Y",        // change scale of output
-            //     "transpose=1",      // rotate 90 degrees clockwise
-            //     "crop=0,0,0,0",     // crop something
-            // ])
-            .output(
-                Output::from(out)
-                    .set_framerate(ez_ffmpeg::AVRational {
-                        num: frame_rate as i32,
-                        den: 1,
-                    })
-                    .set_audio_codec("mp3"),
+        audio: Option<(&str, AudioWindow)>,
+    ) -> JoinHandle<()> {
+        let out = out.to_string();
+        let is_gif = out.ends_with(".gif");
+
+        // audio is only kept if the container has a use for it.
+        let audio = audio
+            .zip(Self::audio_codec_for(&out))
+            .map(|((path, window), codec)| (path.to_string(), window, codec));
+
+        std::thread::spawn(move || {
+            let mut current: Vec<u8> = Vec::new();
+            let mut cursor = 0;
+
+            let input = Input::new_by_read_callback(move |buf: &mut [u8]| {
+                if cursor == current.len() {
+                    match frames.recv() {
+                        Ok(frame) => {
+                            current = frame;
+                            cursor = 0;
+                        }
+                        Err(_) => return AVERROR_EOF,
+                    }
+                }
+
+                let n = buf.len().min(current.len() - cursor);
+                buf[..n].copy_from_slice(&current[cursor..cursor + n]);
+                cursor += n;
+                n as i32
+            })
+            .set_format("rawvideo")
+            .set_input_opt("pixel_format", "rgba")
+            .set_input_opt("video_size", format!("{width}x{height}"))
+            .set_input_opt(
+                "framerate",
+                format!("{}/{}", frame_rate.num, frame_rate.den),
             );
 
-        // implement audio support?
-        let audio_path = ffmpeg_path.audio_path();
-        // println!("TRIED TO PARSE: {audio_path}");
+            // ffmpeg -f rawvideo -pixel_format rgba -video_size WxH -framerate N/D -i - out.gif
+            let mut output = Output::from(out.as_str())
+                .set_framerate(frame_rate)
+                .add_stream_map(ENCODED_LABEL);
+
+            // GIFs have a fixed codec, and looping only means something to them.
+            if is_gif {
+                if let Some(loop_count) = settings.loop_count {
+                    output = output.set_format_opt("loop", loop_count.to_string());
+                }
+            } else {
+                if let Some(codec) = &settings.codec {
+                    output = output.set_video_codec(codec);
+                }
+                if let Some(crf) = settings.crf {
+                    output = output.set_video_codec_opt("crf", crf.to_string());
+                }
+                if let Some(bitrate) = &settings.bitrate {
+                    output = output.set_video_codec_opt("b", bitrate);
+                }
+            }
+
+            let mut context = FfmpegContext::builder()
+                .input(input)
+                .filter_desc(Self::encode_filter(&settings, is_gif, skip_transparent));
+
+            if let Some((audio_path, window, codec)) = audio {
+                let mut audio_input = Input::from(audio_path);
+                if let Some(start) = window.start_us {
+                    audio_input = audio_input.set_start_time_us(start);
+                }
+                if let Some(duration) = window.duration_us {
+                    audio_input = audio_input.set_recording_time_us(duration);
+                }
+
+                context = context.input(audio_input);
+                output = output.add_stream_map("1:a").set_audio_codec(codec);
+            }
+
+            let context = context.output(output).build().unwrap();
+
+            FfmpegScheduler::new(context)
+                .start()
+                .unwrap()
+                .wait()
+                .unwrap();
+        })
+    }
+
+    fn to_us(seconds: f64) -> i64 {
+        (seconds * 1_000_000.0).round() as i64
+    }
 
-        if audio {
-            context = context.input(Input::from(audio_path));
+    /// The frame rate left over after only keeping every `step`th frame.
+    pub fn stepped_frame_rate(frame_rate: AVRational, step: usize) -> AVRational {
+        AVRational {
+            num: frame_rate.num,
+            den: frame_rate.den * step as i32,
         }
+    }
 
-        let context = context.build().unwrap();
+    /// The part of the source's audio that lines up with the trimmed video.
+    fn audio_window(trim: &Trim, frame_rate: AVRational) -> AudioWindow {
+        let max_frames_time = trim
+            .max_frames
+            .map(|frames| frames as f64 * frame_rate.den as f64 / frame_rate.num as f64);
 
-        FfmpegScheduler::new(context)
-            .start()
-            .unwrap()
-            .wait()
-            .unwrap();
+        let duration = match (trim.recording_time(), max_frames_time) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        AudioWindow {
+            start_us: trim.start.map(Self::to_us),
+            duration_us: duration.map(Self::to_us),
+        }
+    }
+
+    /// Builds the filtergraph applied to frames right before they're encoded.
+    fn encode_filter(settings: &FfmpegConfig, is_gif: bool, skip_transparent: bool) -> String {
+        let mut filters = Vec::new();
+
+        if is_gif {
+            if let Some(palette) = settings.gif_palette.filter(skip_transparent) {
+                filters.push(palette);
+            }
+        } else if let Some(pixel_format) = &settings.pixel_format {
+            filters.push(format!("format={pixel_format}"));
+        }
+
+        if filters.is_empty() {
+            filters.push("null".to_string());
+        }
+
+        format!("[0:v]{}[{ENCODED_LABEL}]", filters.join(","))
     }
 
     pub fn get_video_info(input: &str) -> VideoInfo {
         let meta = ez_ffmpeg::stream_info::find_video_stream_info(input).unwrap();
         let stream_info = meta.unwrap();
-        let (fps, nb_frames) = match stream_info {
-            StreamInfo::Video { fps, nb_frames, .. } => (fps, nb_frames),
+        let (fps, avg_frame_rate, r_frame_rate, width, height, rotate) = match stream_info {
+            StreamInfo::Video {
+                fps,
+                avg_frame_rate,
+                r_frame_rate,
+                width,
+                height,
+                rotate,
+                ..
+            } => (fps, avg_frame_rate, r_frame_rate, width, height, rotate),
             _ => unimplemented!("???"),
         };
 
+        // the average rate keeps the duration of variable-frame-rate sources intact.
+        // `nb_frames` isn't used at all, since plenty of containers leave it empty.
+        let frame_rate = [avg_frame_rate, r_frame_rate]
+            .into_iter()
+            .find(|rate| rate.num > 0 && rate.den > 0)
+            .unwrap_or(AVRational {
+                num: (fps * 1000.0).round() as i32,
+                den: 1000,
+            });
+
+        // ffmpeg auto-rotates while decoding, so sideways videos come out with swapped dimensions.
+        let (width, height) = if rotate % 180 == 0 {
+            (width as u32, height as u32)
+        } else {
+            (height as u32, width as u32)
+        };
+
         VideoInfo {
-            fps,
-            frame_count: nb_frames,
+            frame_rate,
+            width,
+            height,
+        }
+    }
+
+    /// The frame rate that plays every frame of an animated image (e.g. a GIF) for its own
+    /// delay, given the delays in milliseconds.
+    ///
+    /// This is the largest rate that evenly divides every delay, so frames with longer
+    /// delays end up being repeated rather than stretched. Delays are rounded to
+    /// [`MIN_DELAY_STEP_MS`] first, so it never goes above 50fps.
+    pub fn frame_rate_from_delays(delays: &[u32]) -> AVRational {
+        fn gcd(a: u32, b: u32) -> u32 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+
+        let step = delays
+            .iter()
+            .map(|&delay| Self::round_delay(delay))
+            .fold(0, gcd)
+            .max(MIN_DELAY_STEP_MS);
+
+        AVRational {
+            num: 1000,
+            den: step as i32,
         }
     }
 
+    /// The time between frames at `frame_rate`, in milliseconds.
+    pub fn step_ms(frame_rate: AVRational) -> u32 {
+        (1000.0 * frame_rate.den as f64 / frame_rate.num as f64).round() as u32
+    }
+
+    /// Rounds a delay in milliseconds to the nearest multiple of [`MIN_DELAY_STEP_MS`].
+    pub fn round_delay(delay: u32) -> u32 {
+        let steps = (delay as f64 / MIN_DELAY_STEP_MS as f64).round().max(1.0);
+        steps as u32 * MIN_DELAY_STEP_MS
+    }
+
     // dummy function to list all properties of a stream_info video
     fn get_video_stream_info(input: &str) {
         let stream_info = ez_ffmpeg::stream_info::find_video_stream_info(input)
@@ -206,20 +602,74 @@ impl FfmpegPathUtil {
         format!("{}/{}", dir, self.prefix)
     }
 
-    pub fn frame_path(&self) -> String {
-        format!("{}/frame-%04d.png", self.dir())
+    /// Where sources that only exist in memory (e.g. downloads) are written for ffmpeg.
+    pub fn source_path(&self) -> String {
+        format!("{}/source", self.dir())
     }
 
-    pub fn frame_path_rs(&self, i: i64) -> String {
-        format!("{}/frame-{i:04}.png", self.dir())
+    /// Matroska is used since it can hold any audio codec without re-encoding.
+    pub fn audio_path(&self) -> String {
+        format!("{}/audio.mka", self.dir())
     }
+}
 
-    pub fn audio_path(&self) -> String {
-        format!("{}/source/audio.mp3", self._dir())
+#[derive(Clone, Copy, Debug)]
+pub struct AudioWindow {
+    pub start_us: Option<i64>,
+    pub duration_us: Option<i64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct VideoInfo {
+    /// The exact (constant) rate frames are decoded and encoded at.
+    pub frame_rate: AVRational,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl VideoInfo {
+    pub fn fps(&self) -> f64 {
+        self.frame_rate.num as f64 / self.frame_rate.den as f64
     }
 }
 
-struct VideoInfo {
-    fps: f64,
-    frame_count: i64,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(delays: &[u32]) -> (i32, i32) {
+        let rate = FfmpegUtil::frame_rate_from_delays(delays);
+        (rate.num, rate.den)
+    }
+
+    #[test]
+    fn even_delays_keep_their_rate() {
+        assert_eq!(rate(&[100, 100, 100]), (1000, 100));
+        assert_eq!(rate(&[40, 80, 120]), (1000, 40));
+    }
+
+    #[test]
+    fn step_comes_from_the_ratio() {
+        let rate = FfmpegUtil::frame_rate_from_delays(&[40, 80, 120]);
+        assert_eq!(FfmpegUtil::step_ms(rate), 40);
+        assert_eq!(FfmpegUtil::step_ms(AVRational { num: 25, den: 1 }), 40);
+        assert_eq!(FfmpegUtil::step_ms(AVRational { num: 50, den: 2 }), 40);
+    }
+
+    #[test]
+    fn step_never_drops_below_the_minimum() {
+        // 70 and 100 would otherwise share a 10ms step.
+        assert_eq!(rate(&[70, 100]), (1000, 20));
+        assert_eq!(rate(&[30, 50]), (1000, 20));
+        assert_eq!(rate(&[]), (1000, 20));
+    }
+
+    #[test]
+    fn delays_round_to_the_nearest_step() {
+        assert_eq!(FfmpegUtil::round_delay(20), 20);
+        assert_eq!(FfmpegUtil::round_delay(29), 20);
+        assert_eq!(FfmpegUtil::round_delay(30), 40);
+        assert_eq!(FfmpegUtil::round_delay(1), 20);
+        assert_eq!(FfmpegUtil::round_delay(1000), 1000);
+    }
 }
@@ -0,0 +1,203 @@
+use std::{collections::HashMap, io::Write};
+
+use image::RgbaImage;
+use png::{BitDepth, ColorType, Compression, Encoder, EncodingError};
+
+/// An image split into a palette of up to 256 colours, and an index into it per pixel.
+///
+/// Dithered images only use the colours of their palette, so storing them like this
+/// is lossless - and much smaller than full RGBA.
+pub struct IndexedImage {
+    width: u32,
+    height: u32,
+    palette: Vec<[u8; 4]>,
+    indices: Vec<u8>,
+}
+
+impl IndexedImage {
+    /// Indexes an image, if it has few enough colours.
+    ///
+    /// With `skip_transparent`, every fully transparent pixel shares one entry - whatever
+    /// colour they had can't be seen anyway.
+    pub fn from_image(image: &RgbaImage, skip_transparent: bool) -> Option<Self> {
+        let mut lookup: HashMap<[u8; 4], u8> = HashMap::new();
+        let mut palette = Vec::new();
+        let mut indices = Vec::with_capacity((image.width() * image.height()) as usize);
+
+        for pixel in image.pixels() {
+            let colour = if skip_transparent && pixel.0[3] == 0 {
+                [0, 0, 0, 0]
+            } else {
+                pixel.0
+            };
+
+            let index = match lookup.get(&colour) {
+                Some(index) => *index,
+                None => {
+                    if palette.len() == 256 {
+                        return None;
+                    }
+
+                    let index = palette.len() as u8;
+                    lookup.insert(colour, index);
+                    palette.push(colour);
+                    index
+                }
+            };
+
+            indices.push(index);
+        }
+
+        Some(Self {
+            width: image.width(),
+            height: image.height(),
+            palette,
+            indices,
+        })
+    }
+
+    /// The smallest bit depth that can index every colour.
+    fn bit_depth(&self) -> BitDepth {
+        match self.palette.len() {
+            0..=2 => BitDepth::One,
+            3..=4 => BitDepth::Two,
+            5..=16 => BitDepth::Four,
+            _ => BitDepth::Eight,
+        }
+    }
+
+    /// Packs the indices into rows of `bits` bits per pixel, most significant first.
+    /// Each row starts on a new byte.
+    fn packed(&self, bits: usize) -> Vec<u8> {
+        if self.width == 0 {
+            return Vec::new();
+        }
+
+        let per_byte = 8 / bits;
+        let row_bytes = (self.width as usize).div_ceil(per_byte);
+        let mut packed = vec![0u8; row_bytes * self.height as usize];
+
+        for (y, row) in self.indices.chunks(self.width as usize).enumerate() {
+            for (x, index) in row.iter().enumerate() {
+                let shift = 8 - bits * (x % per_byte + 1);
+                packed[y * row_bytes + x / per_byte] |= *index << shift;
+            }
+        }
+
+        packed
+    }
+
+    pub fn write_png<W: Write>(
+        &self,
+        writer: W,
+        compression: Compression,
+    ) -> Result<(), EncodingError> {
+        let depth = self.bit_depth();
+
+        let mut encoder = Encoder::new(writer, self.width, self.height);
+        encoder.set_color(ColorType::Indexed);
+        encoder.set_depth(depth);
+        encoder.set_compression(compression);
+        encoder.set_palette(
+            self.palette
+                .iter()
+                .flat_map(|[r, g, b, _]| [*r, *g, *b])
+                .collect::<Vec<u8>>(),
+        );
+
+        // transparency is only stored up to the last colour that needs it.
+        let alphas: Vec<u8> = self.palette.iter().map(|[_, _, _, a]| *a).collect();
+        if let Some(last) = alphas.iter().rposition(|a| *a != 255) {
+            encoder.set_trns(alphas[..=last].to_vec());
+        }
+
+        let data = match depth {
+            BitDepth::Eight => self.indices.clone(),
+            _ => self.packed(depth as usize),
+        };
+
+        encoder.write_header()?.write_image_data(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    fn indexed(width: u32, height: u32, colours: usize, indices: Vec<u8>) -> IndexedImage {
+        IndexedImage {
+            width,
+            height,
+            palette: vec![[0, 0, 0, 255]; colours],
+            indices,
+        }
+    }
+
+    #[test]
+    fn packs_rows_most_significant_first() {
+        let image = indexed(3, 2, 2, vec![1, 0, 1, 0, 1, 1]);
+        assert_eq!(image.packed(1), vec![0b1010_0000, 0b0110_0000]);
+
+        let image = indexed(3, 1, 4, vec![3, 1, 2]);
+        assert_eq!(image.packed(2), vec![0b1101_1000]);
+
+        let image = indexed(3, 1, 16, vec![0xA, 0x5, 0xF]);
+        assert_eq!(image.packed(4), vec![0xA5, 0xF0]);
+    }
+
+    #[test]
+    fn rows_start_on_a_new_byte() {
+        let image = indexed(9, 2, 2, vec![1; 18]);
+        assert_eq!(image.packed(1), vec![0xFF, 0x80, 0xFF, 0x80]);
+    }
+
+    #[test]
+    fn empty_images_pack_to_nothing() {
+        assert!(indexed(0, 0, 1, vec![]).packed(1).is_empty());
+        assert!(indexed(0, 4, 1, vec![]).packed(2).is_empty());
+    }
+
+    #[test]
+    fn bit_depth_fits_the_palette() {
+        assert_eq!(indexed(1, 1, 2, vec![0]).bit_depth(), BitDepth::One);
+        assert_eq!(indexed(1, 1, 3, vec![0]).bit_depth(), BitDepth::Two);
+        assert_eq!(indexed(1, 1, 16, vec![0]).bit_depth(), BitDepth::Four);
+        assert_eq!(indexed(1, 1, 17, vec![0]).bit_depth(), BitDepth::Eight);
+    }
+
+    #[test]
+    fn colours_are_indexed_in_order() {
+        let image = RgbaImage::from_fn(3, 1, |x, _| match x {
+            1 => Rgba([0, 0, 255, 128]),
+            _ => Rgba([255, 0, 0, 255]),
+        });
+
+        let indexed = IndexedImage::from_image(&image, false).unwrap();
+        assert_eq!(indexed.palette, vec![[255, 0, 0, 255], [0, 0, 255, 128]]);
+        assert_eq!(indexed.indices, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn transparent_pixels_share_an_entry() {
+        let image = RgbaImage::from_fn(3, 1, |x, _| match x {
+            0 => Rgba([255, 0, 0, 0]),
+            1 => Rgba([0, 255, 0, 0]),
+            _ => Rgba([0, 0, 255, 255]),
+        });
+
+        let skipped = IndexedImage::from_image(&image, true).unwrap();
+        assert_eq!(skipped.palette.len(), 2);
+        assert_eq!(skipped.indices, vec![0, 0, 1]);
+
+        let kept = IndexedImage::from_image(&image, false).unwrap();
+        assert_eq!(kept.palette.len(), 3);
+    }
+
+    #[test]
+    fn too_many_colours_are_not_indexed() {
+        let image = RgbaImage::from_fn(257, 1, |x, _| Rgba([x as u8, (x >> 8) as u8, 0, 255]));
+        assert!(IndexedImage::from_image(&image, true).is_none());
+    }
+}
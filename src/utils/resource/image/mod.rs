@@ -1,29 +1,50 @@
+pub mod indexed;
 pub mod parser;
+pub mod text;
 
-use image::{DynamicImage, GenericImageView};
-use image_effects::dispatch::EffectEnum;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use image::{
+    codecs::png::{FilterType, PngEncoder},
+    DynamicImage, GenericImageView, ImageOutputFormat,
+};
 
 use crate::{
-    parsers::v2::structure::meta::{SizeConstraint, SourceKind},
-    utils::resource::image::parser::ImageParser,
+    parsers::v2::structure::{
+        alpha::AlphaConfig,
+        meta::{Output, SizeConstraint, SourceKind},
+        output::{IndexedMode, OutputFormat},
+    },
+    utils::{
+        pipeline::Pipeline,
+        resource::{
+            ffmpeg::utils::FfmpegUtil,
+            image::{indexed::IndexedImage, parser::ImageParser},
+        },
+    },
 };
 
 #[derive(Clone)]
 pub struct ImageResource {
     image: DynamicImage,
+    alpha: AlphaConfig,
 }
 
 impl From<DynamicImage> for ImageResource {
     fn from(value: DynamicImage) -> Self {
-        Self { image: value }
+        Self {
+            image: value,
+            alpha: AlphaConfig::default(),
+        }
     }
 }
 
 impl ImageResource {
     pub fn use_source(source: &SourceKind) -> Self {
-        Self {
-            image: ImageParser::parse_file(&source).into_image(),
-        }
+        ImageParser::parse_file(&source).into_image().into()
     }
 
     pub fn constrain(mut self, constrain: &SizeConstraint) -> Self {
@@ -31,16 +52,75 @@ impl ImageResource {
         self
     }
 
-    pub fn apply_effects(mut self, effects: Vec<EffectEnum<DynamicImage>>) -> Self {
-        for effect in effects.into_iter() {
-            self.image = effect.affect(self.image);
-        }
+    pub fn apply_effects(mut self, effects: Pipeline, alpha: &AlphaConfig) -> Self {
+        self.image = alpha.preserve(self.image, |image| effects.apply(image));
+        self.alpha = alpha.clone();
 
         self
     }
 
-    pub fn save(&self, out: &str) {
-        self.image.save(format!("{}.png", out)).unwrap();
+    pub fn extension(&self, output: &Output) -> &'static str {
+        output.format.extension()
+    }
+
+    pub fn save(&self, path: &str, output: &Output) {
+        if output.format == OutputFormat::Avif {
+            // `image` can't encode AVIF, so ffmpeg converts a lossless copy.
+            let temp = format!("{path}.tmp.png");
+            self.image.save(&temp).unwrap();
+            FfmpegUtil::encode_avif(&temp, path, output.quality);
+            std::fs::remove_file(temp).unwrap();
+            return;
+        }
+
+        let mut file = BufWriter::new(File::create(path).unwrap());
+
+        if let OutputFormat::Txt | OutputFormat::Ansi = output.format {
+            let text = text::render(
+                &self.image,
+                &output.text,
+                output.format == OutputFormat::Ansi,
+            );
+            file.write_all(text.as_bytes())
+                .unwrap_or_else(|e| panic!("couldn't save [{path}]: {e}"));
+            return;
+        }
+
+        if output.format == OutputFormat::Png && output.indexed != IndexedMode::Never {
+            match IndexedImage::from_image(&self.image.to_rgba8(), self.alpha.skip_transparent) {
+                Some(indexed) => {
+                    indexed
+                        .write_png(file, output.compression.to_png_compression())
+                        .unwrap_or_else(|e| panic!("couldn't save [{path}]: {e}"));
+                    return;
+                }
+                None if output.indexed == IndexedMode::Always => {
+                    eprintln!("[ ! ] | [{path}] has over 256 colours, so can't be indexed.");
+                }
+                None => {}
+            }
+        }
+
+        match output.format {
+            OutputFormat::Png => self.image.write_with_encoder(PngEncoder::new_with_quality(
+                file,
+                output.compression.to_compression_type(),
+                FilterType::Adaptive,
+            )),
+            // jpeg has no alpha channel.
+            OutputFormat::Jpeg => DynamicImage::ImageRgb8(self.image.to_rgb8()).write_to(
+                &mut file,
+                ImageOutputFormat::Jpeg(output.quality.unwrap_or(90)),
+            ),
+            OutputFormat::Webp => DynamicImage::ImageRgba8(self.image.to_rgba8())
+                .write_to(&mut file, ImageOutputFormat::WebP),
+            OutputFormat::Qoi => DynamicImage::ImageRgba8(self.image.to_rgba8())
+                .write_to(&mut file, ImageOutputFormat::Qoi),
+            OutputFormat::Tiff => self.image.write_to(&mut file, ImageOutputFormat::Tiff),
+            OutputFormat::Bmp => self.image.write_to(&mut file, ImageOutputFormat::Bmp),
+            OutputFormat::Avif | OutputFormat::Txt | OutputFormat::Ansi => unreachable!(),
+        }
+        .unwrap_or_else(|e| panic!("couldn't save [{path}]: {e}"));
     }
 
     pub fn get_dimensions(&self) -> (u32, u32) {
@@ -1,62 +1,197 @@
-use std::str::FromStr;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{Cursor, Read, Seek, SeekFrom},
+    str::FromStr,
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread::JoinHandle,
+};
 
-use image::{codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, Frame, GenericImageView};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, Frame, Frames, GenericImageView, RgbaImage,
+};
+use lazy_static::lazy_static;
 use mime::Mime;
-use reqwest::header::{HeaderMap, CONTENT_TYPE};
 
-use crate::parsers::v2::structure::meta::SourceKind;
+use crate::{
+    parsers::v2::structure::{
+        fetch::FetchConfig,
+        meta::{SourceKind, Trim},
+    },
+    utils::resource::{
+        fetch::{Fetched, Fetcher},
+        ffmpeg::{
+            processor::FfmpegProcessor,
+            utils::{FfmpegPathUtil, FfmpegUtil},
+        },
+    },
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ImageKind {
     Image,
     Gif,
+    /// An animated PNG.
+    Apng,
+    /// An animated WebP - these are decoded by `image` rather than ffmpeg.
+    AnimWebp,
+    /// Video containers, such as mp4, mkv or webm.
     Anim,
 }
 
 impl ImageKind {
-    pub fn from_path(path: &str) -> Self {
-        match path.split(".").collect::<Vec<&str>>().last() {
-            Some(v) => Self::from_extension(v),
-            None => todo!("extension is required"),
+    /// Works out the kind of a source from its contents, falling back on a guess from
+    /// its name or headers when the bytes aren't recognised.
+    ///
+    /// Panics with the source's name if neither gives a supported format.
+    pub fn detect(bytes: &[u8], fallback: Option<Self>, origin: &str) -> Self {
+        match Self::from_bytes(bytes).or(fallback) {
+            Some(kind) => kind,
+            None => panic!(
+                "couldn't recognise [{origin}] as a supported format - expected an image \
+                (png, jpeg, gif, webp, tiff, bmp, qoi) or a video (mp4, mov, avi, mkv, webm)"
+            ),
         }
     }
 
-    pub fn from_headers(headers: &HeaderMap) -> Self {
-        match headers.get(CONTENT_TYPE) {
-            None => {
-                todo!("handle case where no content type is present")
+    /// Guesses the kind of a path or URL from its extension, ignoring any query string.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.split(['?', '#']).next().unwrap_or(path);
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        match name.rsplit_once('.') {
+            Some((_, ext)) => Self::from_extension(&ext.to_lowercase()),
+            None => None,
+        }
+    }
+
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        Self::from_mime(Mime::from_str(content_type).ok()?)
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "gif" => Some(Self::Gif),
+            "apng" => Some(Self::Apng),
+            "png" | "jpeg" | "jpg" | "tiff" | "tif" | "bmp" | "webp" | "qoi" => Some(Self::Image),
+            "mp4" | "mov" | "avi" | "webm" | "mkv" | "m4v" => Some(Self::Anim),
+            _ => None,
+        }
+    }
+
+    /// Recognises a format from the first bytes of a file.
+    ///
+    /// Animated PNGs and WebPs are told apart from static ones by their chunks, so
+    /// this needs more than just the magic number for those.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(Self::Gif)
+        } else if bytes.starts_with(Self::PNG_SIGNATURE) {
+            Some(if Self::png_is_animated(Cursor::new(bytes)) {
+                Self::Apng
+            } else {
+                Self::Image
+            })
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF])
+            || bytes.starts_with(b"II*\0")
+            || bytes.starts_with(b"MM\0*")
+            || Self::is_bmp(bytes)
+            || bytes.starts_with(b"qoif")
+        {
+            // jpeg, tiff (either byte order), bmp and qoi are only ever still images.
+            Some(Self::Image)
+        } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" {
+            match &bytes[8..12] {
+                b"WEBP" => {
+                    // the extended format (VP8X) has a flag for animation.
+                    let animated =
+                        bytes.len() > 20 && &bytes[12..16] == b"VP8X" && bytes[20] & 0x02 != 0;
+                    Some(if animated {
+                        Self::AnimWebp
+                    } else {
+                        Self::Image
+                    })
+                }
+                b"AVI " => Some(Self::Anim),
+                _ => None,
             }
-            Some(content_type) => {
-                let content_type = Mime::from_str(content_type.to_str().unwrap()).unwrap();
-                Self::from_mime(content_type)
+        } else if bytes.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+            // EBML - both matroska and webm.
+            Some(Self::Anim)
+        } else if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" {
+            // the ISO base media format is shared by videos and still images like AVIF/HEIC.
+            match &bytes[8..12] {
+                b"avif" | b"heic" | b"heix" | b"mif1" => Some(Self::Image),
+                _ => Some(Self::Anim),
             }
+        } else if bytes.len() >= 8 && matches!(&bytes[4..8], b"moov" | b"mdat" | b"wide" | b"free")
+        {
+            // older quicktime files start straight in with an atom rather than `ftyp`.
+            Some(Self::Anim)
+        } else {
+            None
         }
     }
 
-    pub fn from_extension(ext: &str) -> Self {
-        match ext {
-            "gif" => Self::Gif,
-            "png" | "jpeg" | "jpg" | "tiff" | "bmp" => Self::Image,
-            "mp4" | "mov" | "avi" => Self::Anim,
-            _ => todo!("no support for extension [{ext}]"),
+    const PNG_SIGNATURE: &'static [u8] = b"\x89PNG\r\n\x1a\n";
+
+    /// An APNG is a PNG with an `acTL` chunk before its image data.
+    ///
+    /// This seeks past each chunk's data, so a file can be checked without reading it all.
+    fn png_is_animated(mut png: impl Read + Seek) -> bool {
+        if png.seek(SeekFrom::Start(8)).is_err() {
+            return false;
         }
+
+        let mut chunk = [0; 8];
+        while png.read_exact(&mut chunk).is_ok() {
+            let length = u32::from_be_bytes(chunk[0..4].try_into().unwrap());
+            match &chunk[4..8] {
+                b"acTL" => return true,
+                b"IDAT" => return false,
+                // skipping the data and its CRC.
+                _ => {
+                    if png.seek(SeekFrom::Current(length as i64 + 4)).is_err() {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    /// "BM" alone turns up at the start of plenty of other files, so the size of the
+    /// header after it has to be one a BMP can have, with the pixels starting past it.
+    fn is_bmp(bytes: &[u8]) -> bool {
+        if bytes.len() < 18 || !bytes.starts_with(b"BM") {
+            return false;
+        }
+
+        let field = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let (offset, header_size) = (field(10), field(14));
+
+        matches!(header_size, 12 | 16 | 40 | 52 | 56 | 64 | 108 | 124) && offset >= 14 + header_size
     }
 
-    pub fn from_mime(mime: Mime) -> Self {
+    pub fn from_mime(mime: Mime) -> Option<Self> {
         match (mime.type_(), mime.subtype()) {
-            (mime::IMAGE, mime::GIF) => Self::Gif,
-            (mime::VIDEO, _) => Self::Anim,
-            (mime::IMAGE, _) => Self::Image,
-            _ => todo!("no support for mime-type {mime}"),
+            (mime::IMAGE, mime::GIF) => Some(Self::Gif),
+            (mime::IMAGE, subtype) if subtype == "apng" => Some(Self::Apng),
+            (mime::VIDEO, _) => Some(Self::Anim),
+            (mime::IMAGE, _) => Some(Self::Image),
+            // generic types like `application/octet-stream` say nothing useful.
+            _ => None,
         }
     }
 }
 
-#[derive(Clone)]
 pub enum ImageResult {
     Image(DynamicImage),
     Anim(Vec<DynamicImage>),
     Gif(Vec<Frame>),
+    /// Videos are decoded as their frames are read, rather than all at once.
+    Video(VideoFrames),
 }
 
 impl ImageResult {
@@ -84,10 +219,21 @@ impl ImageResult {
         }
     }
 
+    pub fn into_video(self) -> VideoFrames {
+        match self {
+            ImageResult::Video(video) => video,
+            ImageResult::Image(_) => unreachable!("expected a video, but got a still image"),
+            ImageResult::Anim(_) | ImageResult::Gif(_) => {
+                unreachable!("expected a video, but got an animated image")
+            }
+        }
+    }
+
     pub fn get_dimensions(&self) -> (u32, u32) {
         match self {
             Self::Image(img) => img.dimensions(),
             Self::Anim(img) => img.get(0).unwrap().dimensions(),
+            Self::Video(video) => video.dimensions,
             Self::Gif(gif) => {
                 let frame = gif.get(0).unwrap();
                 (frame.top(), frame.left())
@@ -108,62 +254,279 @@ impl From<Vec<Frame>> for ImageResult {
     }
 }
 
+/// The frames of a video held in memory, decoded on a separate thread as they're read.
+///
+/// Like [`FfmpegProcessor`], only a bounded window of frames is decoded ahead of the reader.
+pub struct VideoFrames {
+    pub dimensions: (u32, u32),
+    frames: Option<Receiver<RgbaImage>>,
+    decoder: Option<JoinHandle<()>>,
+    path_util: FfmpegPathUtil,
+}
+
+impl Iterator for VideoFrames {
+    type Item = DynamicImage;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.as_ref()?.recv().ok();
+        frame.map(DynamicImage::ImageRgba8)
+    }
+}
+
+impl Drop for VideoFrames {
+    fn drop(&mut self) {
+        // dropping the receiver unblocks the decoder if not every frame was read.
+        drop(self.frames.take());
+        if let Some(decoder) = self.decoder.take() {
+            decoder.join().unwrap();
+        }
+
+        FfmpegUtil::clear_temp(&self.path_util.prefix);
+    }
+}
+
+lazy_static! {
+    /// Stdin can only be read once, so it's kept around for every use of the source.
+    static ref STDIN: Vec<u8> = {
+        let mut bytes = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut bytes)
+            .expect("couldn't read the source from stdin");
+        bytes
+    };
+}
+
 pub struct ImageParser {}
 
 impl ImageParser {
     pub fn parse_kind(source: &SourceKind) -> ImageKind {
         match source {
             SourceKind::File(path) => Self::parse_localkind(path),
-            SourceKind::Url(path) => Self::parse_webkind(path),
+            SourceKind::Url(url, fetch) => Self::parse_webkind(url, fetch),
+            SourceKind::Stdin => Self::parse_stdinkind(),
         }
     }
 
     pub fn parse_file(source: &SourceKind) -> ImageResult {
         match source {
             SourceKind::File(path) => Self::parse_localfile(path),
-            SourceKind::Url(path) => Self::parse_webfile(path),
+            SourceKind::Url(url, fetch) => Self::parse_webfile(url, fetch),
+            SourceKind::Stdin => Self::parse_stdinfile(),
         }
     }
 
     pub fn parse_localkind(path: &str) -> ImageKind {
-        ImageKind::from_path(path)
+        // the header is enough to tell formats apart, bar PNGs.
+        let mut header = Vec::with_capacity(4096);
+        let file = std::fs::File::open(path).ok();
+        if let Some(file) = file.as_ref() {
+            let _ = file.take(4096).read_to_end(&mut header);
+        }
+
+        let kind = ImageKind::detect(&header, ImageKind::from_path(path), path);
+        match (kind, file) {
+            // big chunks (colour profiles, text) can push an APNG's `acTL` past the header.
+            (ImageKind::Image, Some(file)) if header.starts_with(ImageKind::PNG_SIGNATURE) => {
+                if ImageKind::png_is_animated(file) {
+                    ImageKind::Apng
+                } else {
+                    ImageKind::Image
+                }
+            }
+            (kind, _) => kind,
+        }
     }
 
     pub fn parse_localfile(path: &str) -> ImageResult {
         let bytes = std::fs::read(path);
         if let Err(res) = &bytes {
-            println!("ERROR READING: {path}");
+            eprintln!("ERROR READING: {path}");
         }
         let bytes = bytes.unwrap();
-        let res = Self::parse_bytes(&bytes, ImageKind::from_path(path));
+        let kind = ImageKind::detect(&bytes, ImageKind::from_path(path), path);
+        let res = Self::parse_bytes(&bytes, kind);
         res
     }
 
-    pub fn parse_webkind(url: &str) -> ImageKind {
-        let response = reqwest::blocking::get(url).unwrap();
-        ImageKind::from_headers(response.headers())
+    pub fn parse_webkind(url: &str, fetch: &FetchConfig) -> ImageKind {
+        Self::web_kind(url, &Fetcher::fetch(url, fetch))
     }
 
-    pub fn parse_webfile(url: &str) -> ImageResult {
-        let response = reqwest::blocking::get(url).unwrap();
-        let headers = response.headers().clone();
-        let bytes = response.bytes().unwrap();
+    pub fn parse_webfile(url: &str, fetch: &FetchConfig) -> ImageResult {
+        let fetched = Fetcher::fetch(url, fetch);
+        let kind = Self::web_kind(url, &fetched);
 
-        let result = Self::parse_bytes(&bytes, ImageKind::from_headers(&headers));
+        Self::parse_bytes(&fetched.bytes, kind)
+    }
 
-        result
+    fn web_kind(url: &str, fetched: &Fetched) -> ImageKind {
+        let fallback = fetched
+            .content_type
+            .as_deref()
+            .and_then(ImageKind::from_content_type)
+            .or_else(|| ImageKind::from_path(url));
+
+        ImageKind::detect(&fetched.bytes, fallback, url)
+    }
+
+    /// There's no extension or header to fall back on, so stdin relies entirely on sniffing.
+    pub fn parse_stdinkind() -> ImageKind {
+        ImageKind::detect(&STDIN, None, "stdin")
+    }
+
+    pub fn parse_stdinfile() -> ImageResult {
+        Self::parse_bytes(&STDIN, Self::parse_stdinkind())
+    }
+
+    /// Reads the raw bytes of a source, wherever it is.
+    pub fn read_bytes(source: &SourceKind) -> Vec<u8> {
+        match source {
+            SourceKind::File(path) => std::fs::read(path).unwrap(),
+            SourceKind::Url(url, fetch) => Fetcher::fetch(url, fetch).bytes.to_vec(),
+            SourceKind::Stdin => STDIN.to_vec(),
+        }
+    }
+
+    /// Decodes the frames of an animated image format with `image`.
+    fn animation_frames(bytes: &[u8], image_kind: ImageKind) -> Frames<'_> {
+        match image_kind {
+            ImageKind::Gif => GifDecoder::new(bytes).unwrap().into_frames(),
+            ImageKind::Apng => PngDecoder::new(bytes).unwrap().apng().into_frames(),
+            ImageKind::AnimWebp => WebPDecoder::new(bytes).unwrap().into_frames(),
+            _ => panic!("{image_kind:?} isn't an animated image format"),
+        }
+    }
+
+    pub fn animation_dimensions(bytes: &[u8], image_kind: ImageKind) -> (u32, u32) {
+        let frame = Self::animation_frames(bytes, image_kind)
+            .next()
+            .unwrap()
+            .unwrap();
+        frame.buffer().dimensions()
+    }
+
+    /// The delay of each frame of an animated image, in milliseconds.
+    ///
+    /// Like browsers (and ffmpeg), delays below 20ms are treated as 100ms.
+    pub fn frame_delays(bytes: &[u8], image_kind: ImageKind) -> Vec<u32> {
+        Self::animation_frames(bytes, image_kind)
+            .map(|frame| Self::delay_ms(&frame.unwrap()))
+            .collect()
+    }
+
+    fn delay_ms(frame: &Frame) -> u32 {
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let ms = (numer as f64 / denom as f64).round() as u32;
+        if ms < 20 {
+            100
+        } else {
+            ms
+        }
+    }
+
+    /// Decodes an animated image on a separate thread, sending each frame as it's decoded.
+    ///
+    /// This mirrors [`FfmpegUtil::split_media_into_frames`] for formats ffmpeg can't read:
+    /// frames are repeated to play at a constant rate of one frame every `step_ms` (with
+    /// their delays rounded the same way as [`FfmpegUtil::frame_rate_from_delays`]), then
+    /// trimmed.
+    pub fn split_animation_into_frames(
+        path: &str,
+        image_kind: ImageKind,
+        step_ms: u32,
+        trim: Trim,
+        frames: SyncSender<RgbaImage>,
+    ) -> JoinHandle<()> {
+        let path = path.to_string();
+
+        std::thread::spawn(move || {
+            let bytes = std::fs::read(&path).unwrap();
+            let fps = 1000.0 / step_ms as f64;
+
+            let skip = trim.start.map(|s| (s * fps).round() as usize).unwrap_or(0);
+            let take = trim
+                .recording_time()
+                .map(|t| (t * fps).round() as usize)
+                .unwrap_or(usize::MAX);
+
+            let expanded = Self::animation_frames(&bytes, image_kind).flat_map(|frame| {
+                let frame = frame.unwrap();
+                let delay = FfmpegUtil::round_delay(Self::delay_ms(&frame));
+                let repeats = (delay / step_ms).max(1) as usize;
+                std::iter::repeat(frame.into_buffer()).take(repeats)
+            });
+
+            for frame in expanded
+                .skip(skip)
+                .take(take)
+                .step_by(trim.step())
+                .take(trim.max_frames.unwrap_or(usize::MAX))
+            {
+                if frames.send(frame).is_err() {
+                    break;
+                }
+            }
+        })
     }
 
     pub fn parse_bytes(bytes: &[u8], image_kind: ImageKind) -> ImageResult {
         let result = match image_kind {
             ImageKind::Gif => Self::gif_from_bytes(&bytes).into(),
             ImageKind::Image => Self::image_from_bytes(&bytes).into(),
-            ImageKind::Anim => todo!("Anim currently unsupported."),
+            ImageKind::Apng | ImageKind::AnimWebp => ImageResult::Anim(
+                Self::animation_frames(bytes, image_kind)
+                    .map(|frame| DynamicImage::ImageRgba8(frame.unwrap().into_buffer()))
+                    .collect(),
+            ),
+            ImageKind::Anim => ImageResult::Video(Self::video_from_bytes(bytes)),
         };
 
         result
     }
 
+    /// Starts decoding a video held in memory.
+    ///
+    /// ffmpeg needs something to seek through, so the bytes are written to a temp file first.
+    /// It's cleared once the frames are dropped.
+    fn video_from_bytes(bytes: &[u8]) -> VideoFrames {
+        let mut hasher = DefaultHasher::new();
+        bytes.hash(&mut hasher);
+
+        let path_util = FfmpegPathUtil {
+            temp: true,
+            prefix: format!("bytes-{}-{:016x}", std::process::id(), hasher.finish()),
+        };
+        let path = path_util.source_path();
+        Self::write_temp(bytes, &path_util);
+
+        let info = FfmpegUtil::get_video_info(&path);
+        let (frame_tx, frame_rx) = mpsc::sync_channel(FfmpegProcessor::frame_window());
+        let decoder = FfmpegUtil::split_media_into_frames(
+            &path,
+            (info.width, info.height),
+            info.frame_rate,
+            Trim::default(),
+            None,
+            frame_tx,
+        );
+
+        VideoFrames {
+            dimensions: (info.width, info.height),
+            frames: Some(frame_rx),
+            decoder: Some(decoder),
+            path_util,
+        }
+    }
+
+    /// Writes bytes to the source path of a temp directory, so ffmpeg has a file to work with.
+    pub fn write_temp(bytes: &[u8], path_util: &FfmpegPathUtil) -> String {
+        std::fs::create_dir_all(path_util.dir()).unwrap();
+        let path = path_util.source_path();
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
     fn image_from_bytes(response: &[u8]) -> DynamicImage {
         image::load_from_memory(response).unwrap()
     }
@@ -176,3 +539,55 @@ impl ImageParser {
             .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A PNG's chunks, minus their CRCs' actual values - nothing here checks them.
+    fn png(chunks: &[(&[u8; 4], usize)]) -> Vec<u8> {
+        let mut bytes = ImageKind::PNG_SIGNATURE.to_vec();
+        for (kind, length) in chunks {
+            bytes.extend((*length as u32).to_be_bytes());
+            bytes.extend(*kind);
+            bytes.extend(vec![0; length + 4]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn actl_is_found_after_big_chunks() {
+        let apng = png(&[(b"IHDR", 13), (b"iCCP", 8192), (b"acTL", 8), (b"IDAT", 16)]);
+        let still = png(&[(b"IHDR", 13), (b"iCCP", 8192), (b"IDAT", 16), (b"acTL", 8)]);
+
+        assert_eq!(ImageKind::from_bytes(&apng), Some(ImageKind::Apng));
+        assert_eq!(ImageKind::from_bytes(&still), Some(ImageKind::Image));
+
+        let path = std::env::temp_dir().join(format!("imgtoy-sniff-{}.png", std::process::id()));
+        std::fs::write(&path, &apng).unwrap();
+        let kind = ImageParser::parse_localkind(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(kind, ImageKind::Apng);
+    }
+
+    #[test]
+    fn truncated_pngs_are_still_images() {
+        let png = png(&[(b"IHDR", 13), (b"tEXt", 100)]);
+
+        assert_eq!(ImageKind::from_bytes(&png[..50]), Some(ImageKind::Image));
+    }
+
+    #[test]
+    fn bmps_need_a_real_header() {
+        let mut bmp = b"BM".to_vec();
+        bmp.extend(1078u32.to_le_bytes()); // file size
+        bmp.extend([0; 4]);
+        bmp.extend(54u32.to_le_bytes()); // pixel offset
+        bmp.extend(40u32.to_le_bytes()); // BITMAPINFOHEADER
+
+        assert_eq!(ImageKind::from_bytes(&bmp), Some(ImageKind::Image));
+        assert_eq!(ImageKind::from_bytes(b"BMW M3 brochure, page 1"), None);
+        assert_eq!(ImageKind::from_bytes(b"BM"), None);
+    }
+}
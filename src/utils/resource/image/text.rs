@@ -0,0 +1,41 @@
+use std::fmt::Write;
+
+use image::DynamicImage;
+
+use crate::{
+    parsers::v2::structure::output::TextOutput,
+    utils::pipeline::ascii::{character, grid, TEXT_ASPECT},
+};
+
+/// Renders the image as lines of text - coloured with 24-bit ANSI escapes if `ansi` is set.
+pub fn render(image: &DynamicImage, text: &TextOutput, ansi: bool) -> String {
+    let cells = grid(image, &text.grid, TEXT_ASPECT);
+    let mut out = String::new();
+
+    for row in cells.rows() {
+        let mut current = None;
+
+        for pixel in row {
+            let [r, g, b, a] = pixel.0;
+
+            // see-through cells are left blank.
+            if a < 128 {
+                out.push(' ');
+                continue;
+            }
+
+            if ansi && current != Some([r, g, b]) {
+                write!(out, "\x1b[38;2;{r};{g};{b}m").unwrap();
+                current = Some([r, g, b]);
+            }
+            out.push(character(&text.ramp, [r, g, b], text.invert));
+        }
+
+        if ansi && current.is_some() {
+            out.push_str("\x1b[0m");
+        }
+        out.push('\n');
+    }
+
+    out
+}
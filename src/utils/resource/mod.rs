@@ -1,14 +1,17 @@
-use ::image::DynamicImage;
-use image_effects::dispatch::EffectEnum;
-
 use crate::{
-    parsers::v2::structure::meta::{SizeConstraint, SourceKind},
+    parsers::v2::structure::{
+        alpha::AlphaConfig,
+        ffmpeg::FfmpegConfig,
+        meta::{Output, SizeConstraint, Source},
+    },
     utils::{
+        pipeline::Pipeline,
         resource::image::parser::ImageKind,
         resource::{ffmpeg::processor::FfmpegProcessor, image::ImageResource},
     },
 };
 
+pub mod fetch;
 pub mod ffmpeg;
 pub mod image;
 
@@ -19,13 +22,13 @@ pub enum Resource {
 }
 
 impl Resource {
-    pub fn use_source(source: &SourceKind) -> Self {
-        let image_kind = source.get_image_kind();
+    pub fn use_source(source: &Source, ffmpeg: &FfmpegConfig) -> Self {
+        let image_kind = source.kind.get_image_kind();
 
         match image_kind {
-            ImageKind::Image => Self::ImageResource(ImageResource::use_source(source)),
-            ImageKind::Gif | ImageKind::Anim => {
-                Self::FfmpegProcessor(FfmpegProcessor::use_source(source))
+            ImageKind::Image => Self::ImageResource(ImageResource::use_source(&source.kind)),
+            ImageKind::Gif | ImageKind::Apng | ImageKind::AnimWebp | ImageKind::Anim => {
+                Self::FfmpegProcessor(FfmpegProcessor::use_source(source, ffmpeg))
             }
         }
     }
@@ -48,19 +51,29 @@ impl Resource {
         }
     }
 
-    pub fn apply_effects(self, effects: Vec<EffectEnum<DynamicImage>>) -> Self {
+    pub fn apply_effects(self, effects: Pipeline, alpha: &AlphaConfig) -> Self {
         match self {
             Self::FfmpegProcessor(processor) => {
-                Self::FfmpegProcessor(processor.apply_effects(effects))
+                Self::FfmpegProcessor(processor.apply_effects(effects, alpha))
             }
-            Self::ImageResource(resource) => Self::ImageResource(resource.apply_effects(effects)),
+            Self::ImageResource(resource) => {
+                Self::ImageResource(resource.apply_effects(effects, alpha))
+            }
+        }
+    }
+
+    /// The extension the resource will be saved with.
+    pub fn extension(&self, output: &Output) -> &'static str {
+        match self {
+            Self::FfmpegProcessor(processor) => processor.extension(),
+            Self::ImageResource(resource) => resource.extension(output),
         }
     }
 
-    pub fn save(&self, path: &str) {
+    pub fn save(&self, path: &str, output: &Output) {
         match self {
-            Self::FfmpegProcessor(processor) => processor.save(path),
-            Self::ImageResource(resource) => resource.save(path),
+            Self::FfmpegProcessor(processor) => processor.save(path, output),
+            Self::ImageResource(resource) => resource.save(path, output),
         }
     }
 